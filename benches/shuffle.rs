@@ -0,0 +1,64 @@
+use std::hint::black_box;
+use std::time::Duration;
+use std::time::Instant;
+use winc::prelude::bgra_to_i420;
+use winc::prelude::bgra_to_nv12;
+use winc::prelude::bgra_to_rgba;
+use winc::prelude::bgra_to_rgba_scalar;
+use winc::prelude::yuv420_len;
+use winc::prelude::YuvColorSpace;
+
+// one 1920x1080 frame
+const FRAME_BYTES: usize = 1920 * 1080 * 4;
+const ITERATIONS: u32 = 200;
+
+fn bench(name: &str, mut convert: impl FnMut(&mut [u8])) {
+    let mut data = vec![0u8; FRAME_BYTES];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    // warm up
+    convert(&mut data);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        convert(black_box(&mut data));
+    }
+    let per_frame = start.elapsed() / ITERATIONS;
+    let throughput = FRAME_BYTES as f64 / per_frame.as_secs_f64() / 1e9;
+    println!(
+        "{:<8} {:>10?} per 1080p frame ({:.2} GB/s)",
+        name, per_frame, throughput
+    );
+    assert!(per_frame < Duration::from_secs(1));
+}
+
+fn main() {
+    bench("scalar", bgra_to_rgba_scalar);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("ssse3") {
+            bench("ssse3", |data| unsafe {
+                winc::prelude::bgra_to_rgba_ssse3(data)
+            });
+        }
+        if is_x86_feature_detected!("avx2") {
+            bench("avx2", |data| unsafe {
+                winc::prelude::bgra_to_rgba_avx2(data)
+            });
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    bench("neon", |data| unsafe {
+        winc::prelude::bgra_to_rgba_neon(data)
+    });
+    bench("dispatch", bgra_to_rgba);
+
+    let mut yuv = vec![0u8; yuv420_len(1920, 1080)];
+    bench("i420", |data| {
+        bgra_to_i420(data, 1920, 1080, YuvColorSpace::BT709_LIMITED, &mut yuv).unwrap()
+    });
+    bench("nv12", |data| {
+        bgra_to_nv12(data, 1920, 1080, YuvColorSpace::BT709_LIMITED, &mut yuv).unwrap()
+    });
+}
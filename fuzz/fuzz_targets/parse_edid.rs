@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use winc::prelude::parse_edid;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(edid) = parse_edid(data) {
+        // everything a backend reads from a parsed EDID
+        let _ = (edid.vendor(), edid.model(), edid.serial_string());
+    }
+});
@@ -0,0 +1,170 @@
+use crate::prelude::check_buffer_size;
+use crate::prelude::convert_from_bgra;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::Frame;
+use crate::prelude::FrameBuffer;
+use crate::prelude::FrameInfo;
+use crate::prelude::Metrics;
+use crate::prelude::MonitorInfo;
+use crate::prelude::PixelFormat;
+use crate::prelude::RawImage;
+use crate::prelude::Rect;
+use crate::prelude::Size;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+use crate::prelude::NATIVE_PIXEL_FORMAT;
+
+/// Something that can produce frames of a region of a monitor.
+///
+/// Lets tooling be generic over capture backends, and lets tests substitute fakes.
+pub trait CaptureSource {
+    /// Write a single frame of the region as BGRA into `buffer`, which must hold exactly `width * height * 4` bytes
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()>;
+
+    /// Capture a single frame of the region into a reused buffer, converted to the buffer's format.
+    ///
+    /// The buffer must have the size of the region, see [`FrameBuffer::resize`].
+    /// With [`AlphaPolicy::Drop`] the buffer's format is changed to one without alpha.
+    fn capture_into(
+        &self,
+        frame: &mut FrameBuffer,
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        let size = self.size();
+        if frame.size() != size {
+            return Err(WincError::DimensionMismatch {
+                expected: size,
+                actual: frame.size(),
+            });
+        }
+        let info = self.frame_info();
+        self.capture_bgra_into(frame.bgra_mut(), metrics)?;
+        frame.finish_bgra(self.alpha_policy(), metrics)?;
+        Ok(info)
+    }
+
+    /// Capture a single frame of the region into `buffer` in [`NATIVE_PIXEL_FORMAT`], without allocating.
+    ///
+    /// The layout of `buffer` is fixed, so [`AlphaPolicy::Drop`] keeps alpha as is.
+    fn capture_into_slice(
+        &self,
+        buffer: &mut [u8],
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        let size = self.size();
+        check_buffer_size(size.width as usize * size.height as usize * 4, buffer.len())?;
+        let info = self.frame_info();
+        self.capture_bgra_into(buffer, metrics)?;
+        let alpha = match self.alpha_policy() {
+            AlphaPolicy::Drop => AlphaPolicy::Preserve,
+            alpha => alpha,
+        };
+        convert_from_bgra(buffer, NATIVE_PIXEL_FORMAT, alpha, metrics)?;
+        Ok(info)
+    }
+
+    /// Capture a single frame of the region, converted to `format` while the pixels are still in the backend's buffer
+    fn capture_as(&self, format: PixelFormat, metrics: &mut Metrics) -> WincResult<Frame> {
+        let size = self.size();
+        let mut data = vec![0u8; size.width as usize * size.height as usize * 4];
+        let info = self.frame_info();
+        self.capture_bgra_into(&mut data, metrics)?;
+        let image = RawImage::from_bgra_with_alpha(
+            size.width as u32,
+            size.height as u32,
+            data,
+            format,
+            self.alpha_policy(),
+            metrics,
+        )?;
+        Ok(Frame::new(info, image))
+    }
+
+    /// Capture a single frame of the region in [`NATIVE_PIXEL_FORMAT`]
+    fn capture_raw(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        self.capture_as(NATIVE_PIXEL_FORMAT, metrics)
+    }
+
+    /// Capture a single frame of the region as RGBA, so [`Frame::into_rgba_image`] doesn't copy
+    #[cfg(feature = "image")]
+    fn capture(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        self.capture_as(PixelFormat::Rgba8, metrics)
+    }
+
+    /// The region being captured, in virtual desktop coordinates
+    fn region(&self) -> Rect;
+
+    /// The monitor the region belongs to
+    fn monitor_info(&self) -> &MonitorInfo;
+
+    /// The size of captured frames
+    fn size(&self) -> Size {
+        self.region().size()
+    }
+
+    /// What happens to the alpha the backend writes, applied while converting to the requested format
+    fn alpha_policy(&self) -> AlphaPolicy {
+        AlphaPolicy::default()
+    }
+
+    /// Metadata for a frame that is about to be captured
+    fn frame_info(&self) -> FrameInfo {
+        FrameInfo::now(self.monitor_info().id, self.region())
+    }
+}
+
+/// Lets wrappers like [`ScaledCapturer`](crate::prelude::ScaledCapturer) take boxed sources
+impl<T: CaptureSource + ?Sized> CaptureSource for Box<T> {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        (**self).capture_bgra_into(buffer, metrics)
+    }
+
+    fn capture_into(
+        &self,
+        frame: &mut FrameBuffer,
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        (**self).capture_into(frame, metrics)
+    }
+
+    fn capture_into_slice(
+        &self,
+        buffer: &mut [u8],
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        (**self).capture_into_slice(buffer, metrics)
+    }
+
+    fn capture_as(&self, format: PixelFormat, metrics: &mut Metrics) -> WincResult<Frame> {
+        (**self).capture_as(format, metrics)
+    }
+
+    fn capture_raw(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        (**self).capture_raw(metrics)
+    }
+
+    #[cfg(feature = "image")]
+    fn capture(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        (**self).capture(metrics)
+    }
+
+    fn region(&self) -> Rect {
+        (**self).region()
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        (**self).monitor_info()
+    }
+
+    fn size(&self) -> Size {
+        (**self).size()
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        (**self).alpha_policy()
+    }
+
+    fn frame_info(&self) -> FrameInfo {
+        (**self).frame_info()
+    }
+}
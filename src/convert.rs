@@ -0,0 +1,338 @@
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::uint8x16x3_t;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vld4q_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vst3q_u8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::__m128i;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_loadu_si128;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_or_si128;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_set1_epi32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_setr_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_shuffle_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_storeu_si128;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::__m128i;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_loadu_si128;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_or_si128;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_set1_epi32;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_setr_epi8;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_shuffle_epi8;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_storeu_si128;
+
+use crate::prelude::bgra_to_rgba;
+use crate::prelude::PixelFormat;
+
+/// Convert RGBA to BGRA, the swap is its own inverse
+pub fn rgba_to_bgra(data: &mut [u8]) {
+    bgra_to_rgba(data);
+}
+
+/// Convert BGRA pixels to `format` in place, returning how many bytes of `data` now hold the result.
+///
+/// Formats with fewer bytes per pixel are packed at the start of the buffer,
+/// so no second buffer is needed.
+pub fn convert_bgra_in_place(data: &mut [u8], format: PixelFormat) -> usize {
+    match format {
+        PixelFormat::Bgra8 => data.len() / 4 * 4,
+        PixelFormat::Rgba8 => {
+            bgra_to_rgba(data);
+            data.len() / 4 * 4
+        }
+        PixelFormat::Rgb8 => bgra_to_rgb_in_place(data),
+        PixelFormat::Bgr8 => bgra_to_bgr_in_place(data),
+        PixelFormat::Gray8 => bgra_to_gray_in_place(data),
+    }
+}
+
+/// [`convert_bgra_in_place`], also setting alpha to 255 when `format` keeps it
+pub fn convert_bgra_in_place_opaque(data: &mut [u8], format: PixelFormat) -> usize {
+    match format {
+        PixelFormat::Bgra8 => {
+            swizzle_opaque(data, [0, 1, 2, 3]);
+            data.len() / 4 * 4
+        }
+        PixelFormat::Rgba8 => {
+            swizzle_opaque(data, [2, 1, 0, 3]);
+            data.len() / 4 * 4
+        }
+        _ => convert_bgra_in_place(data, format),
+    }
+}
+
+/// Convert BGRA pixels to `format`, reusing the allocation
+pub fn convert_bgra(mut data: Vec<u8>, format: PixelFormat) -> Vec<u8> {
+    let len = convert_bgra_in_place(&mut data, format);
+    data.truncate(len);
+    data
+}
+
+/// Reorder the 4 channels of every pixel, `order[i]` is the index of the source channel that ends up at `i`.
+///
+/// `bgra_to_rgba` is `swizzle(data, [2, 1, 0, 3])`.
+pub fn swizzle(data: &mut [u8], order: [u8; 4]) {
+    swizzle_with_alpha(data, order, 0)
+}
+
+/// [`swizzle`], then set the last channel of every pixel to 255
+pub fn swizzle_opaque(data: &mut [u8], order: [u8; 4]) {
+    swizzle_with_alpha(data, order, 255)
+}
+
+/// Reorder, then or `alpha` into the last channel
+fn swizzle_with_alpha(data: &mut [u8], order: [u8; 4], alpha: u8) {
+    assert!(
+        order.iter().all(|&channel| channel < 4),
+        "channel index out of range"
+    );
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if is_x86_feature_detected!("ssse3") {
+        return unsafe { swizzle_ssse3(data, order, alpha) };
+    }
+    swizzle_scalar(data, order, alpha)
+}
+
+fn swizzle_scalar(data: &mut [u8], order: [u8; 4], alpha: u8) {
+    for pixel in data.chunks_exact_mut(4) {
+        let source = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        for (channel, &from) in pixel.iter_mut().zip(order.iter()) {
+            *channel = source[from as usize];
+        }
+        pixel[3] |= alpha;
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "ssse3")]
+unsafe fn swizzle_ssse3(data: &mut [u8], order: [u8; 4], alpha: u8) {
+    let mut mask = [0i8; 16];
+    for (i, byte) in mask.iter_mut().enumerate() {
+        *byte = (i / 4 * 4) as i8 + order[i % 4] as i8;
+    }
+    let mask = _mm_loadu_si128(mask.as_ptr() as *const __m128i);
+    let alpha_mask = _mm_set1_epi32(((alpha as u32) << 24) as i32);
+    let mut chunks = data.chunks_exact_mut(16);
+    for chunk in &mut chunks {
+        let mut vector = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+        vector = _mm_shuffle_epi8(vector, mask);
+        vector = _mm_or_si128(vector, alpha_mask);
+        _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, vector);
+    }
+    swizzle_scalar(chunks.into_remainder(), order, alpha);
+}
+
+/// Drop alpha and reorder to RGB, returning the number of bytes written
+pub fn bgra_to_rgb_in_place(data: &mut [u8]) -> usize {
+    pack_3_in_place(data, [2, 1, 0])
+}
+
+/// Drop alpha, returning the number of bytes written
+pub fn bgra_to_bgr_in_place(data: &mut [u8]) -> usize {
+    pack_3_in_place(data, [0, 1, 2])
+}
+
+/// Convert to 8 bit luma using BT.601 weights, returning the number of bytes written
+pub fn bgra_to_gray_in_place(data: &mut [u8]) -> usize {
+    let pixels = data.len() / 4;
+    for i in 0..pixels {
+        let (b, g, r) = (
+            data[i * 4] as u32,
+            data[i * 4 + 1] as u32,
+            data[i * 4 + 2] as u32,
+        );
+        // 0.299 R + 0.587 G + 0.114 B in 8 bit fixed point, weights sum to 256 so white stays 255
+        data[i] = ((77 * r + 150 * g + 29 * b + 128) >> 8) as u8;
+    }
+    pixels
+}
+
+/// Pack each 4 byte pixel down to the 3 channels picked by `order`, front to back
+fn pack_3_in_place(data: &mut [u8], order: [u8; 3]) -> usize {
+    let pixels = data.len() / 4;
+    let done = {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("ssse3") {
+                unsafe { pack_3_ssse3(data, order) }
+            } else {
+                0
+            }
+        }
+        // NEON is mandatory on aarch64
+        #[cfg(target_arch = "aarch64")]
+        {
+            unsafe { pack_3_neon(data, order) }
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            0
+        }
+    };
+    // reading pixel i never sees bytes already overwritten, writes only reach 3i + 2 < 4i
+    for i in done..pixels {
+        let source = [data[i * 4], data[i * 4 + 1], data[i * 4 + 2]];
+        for (j, &from) in order.iter().enumerate() {
+            data[i * 3 + j] = source[from as usize];
+        }
+    }
+    pixels * 3
+}
+
+/// Pack 4 pixels at a time, returning how many pixels were done
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "ssse3")]
+unsafe fn pack_3_ssse3(data: &mut [u8], order: [u8; 3]) -> usize {
+    let [c0, c1, c2] = order.map(|channel| channel as i8);
+    // the top 4 bytes are zeroed, they are overwritten by the next store
+    let mask = _mm_setr_epi8(
+        c0,
+        c1,
+        c2,
+        4 + c0,
+        4 + c1,
+        4 + c2,
+        8 + c0,
+        8 + c1,
+        8 + c2,
+        12 + c0,
+        12 + c1,
+        12 + c2,
+        -1,
+        -1,
+        -1,
+        -1,
+    );
+    let pixels = data.len() / 4;
+    let ptr = data.as_mut_ptr();
+    // each 16 byte store at 3i ends at or before 4i + 16, so it never clobbers unread input
+    let blocks = pixels / 4;
+    for block in 0..blocks {
+        let vector = _mm_loadu_si128(ptr.add(block * 16) as *const __m128i);
+        let vector = _mm_shuffle_epi8(vector, mask);
+        _mm_storeu_si128(ptr.add(block * 12) as *mut __m128i, vector);
+    }
+    blocks * 4
+}
+
+/// Pack 16 pixels at a time, returning how many pixels were done
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn pack_3_neon(data: &mut [u8], order: [u8; 3]) -> usize {
+    let pixels = data.len() / 4;
+    let ptr = data.as_mut_ptr();
+    let blocks = pixels / 16;
+    for block in 0..blocks {
+        let channels = vld4q_u8(ptr.add(block * 64));
+        let channels = [channels.0, channels.1, channels.2, channels.3];
+        let packed = uint8x16x3_t(
+            channels[order[0] as usize],
+            channels[order[1] as usize],
+            channels[order[2] as usize],
+        );
+        // the 48 byte store at 3i ends before 4i + 64, which has already been loaded
+        vst3q_u8(ptr.add(block * 48), packed);
+    }
+    blocks * 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bgra(pixels: usize) -> Vec<u8> {
+        (0..pixels * 4).map(|i| (i * 13 + 5) as u8).collect()
+    }
+
+    fn reference(data: &[u8], format: PixelFormat) -> Vec<u8> {
+        data.chunks_exact(4)
+            .flat_map(|p| {
+                let (b, g, r, a) = (p[0], p[1], p[2], p[3]);
+                let luma = ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8;
+                match format {
+                    PixelFormat::Bgra8 => vec![b, g, r, a],
+                    PixelFormat::Rgba8 => vec![r, g, b, a],
+                    PixelFormat::Rgb8 => vec![r, g, b],
+                    PixelFormat::Bgr8 => vec![b, g, r],
+                    PixelFormat::Gray8 => vec![luma],
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_format_every_length() {
+        for format in PixelFormat::ALL {
+            for pixels in 0..100 {
+                let input = bgra(pixels);
+                let output = convert_bgra(input.clone(), format);
+                assert_eq!(output, reference(&input, format), "{:?} {}", format, pixels);
+                assert_eq!(output.len(), pixels * format.bytes_per_pixel());
+            }
+        }
+    }
+
+    #[test]
+    fn opaque_every_format() {
+        for format in PixelFormat::ALL {
+            let input = bgra(37);
+            let mut output = input.clone();
+            let len = convert_bgra_in_place_opaque(&mut output, format);
+            let mut expected = input.clone();
+            for pixel in expected.chunks_exact_mut(4) {
+                pixel[3] = 255;
+            }
+            assert_eq!(&output[..len], reference(&expected, format), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn gray_keeps_black_and_white() {
+        let mut data = [0, 0, 0, 255, 255, 255, 255, 255].to_vec();
+        assert_eq!(bgra_to_gray_in_place(&mut data), 2);
+        assert_eq!(&data[..2], &[0, 255]);
+    }
+
+    #[test]
+    fn swizzle_matches_scalar() {
+        for order in [[2, 1, 0, 3], [3, 2, 1, 0], [0, 0, 0, 3], [1, 2, 3, 0]] {
+            for pixels in 0..40 {
+                let mut fast = bgra(pixels);
+                let mut slow = fast.clone();
+                swizzle(&mut fast, order);
+                swizzle_scalar(&mut slow, order, 0);
+                assert_eq!(fast, slow);
+                let mut opaque = bgra(pixels);
+                swizzle_opaque(&mut opaque, order);
+                for (pixel, expected) in opaque.chunks_exact(4).zip(slow.chunks_exact(4)) {
+                    assert_eq!(pixel, &[expected[0], expected[1], expected[2], 255]);
+                }
+            }
+        }
+        let mut data = bgra(9);
+        let expected = convert_bgra(data.clone(), PixelFormat::Rgba8);
+        swizzle(&mut data, [2, 1, 0, 3]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn rgba_to_bgra_round_trips() {
+        let input = bgra(17);
+        let mut data = input.clone();
+        bgra_to_rgba(&mut data);
+        rgba_to_bgra(&mut data);
+        assert_eq!(data, input);
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(windows)]
+use crate::prelude::get_monitor_infos;
+use crate::prelude::ContainsPoint;
+use crate::prelude::Intersect;
+use crate::prelude::MonitorId;
+use crate::prelude::MonitorInfo;
+use crate::prelude::Point;
+use crate::prelude::Rect;
+use crate::prelude::Union;
+#[cfg(windows)]
+use crate::prelude::WincResult;
+
+/// The monitors making up the virtual desktop, for mapping between desktop and monitor coordinates.
+///
+/// Desktop coordinates have the primary monitor's top left at the origin,
+/// so monitors left of or above it have negative coordinates.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Desktop {
+    monitors: Vec<MonitorInfo>,
+}
+
+/// The part of a desktop rect that lies on one monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorPiece {
+    pub monitor_id: MonitorId,
+    /// In virtual desktop coordinates
+    pub global: Rect,
+    /// Relative to the monitor's top left
+    pub local: Rect,
+}
+
+impl Desktop {
+    pub fn new(monitors: Vec<MonitorInfo>) -> Self {
+        Desktop { monitors }
+    }
+
+    /// The monitors currently attached
+    #[cfg(windows)]
+    pub fn current() -> WincResult<Self> {
+        Ok(Desktop::new(get_monitor_infos()?))
+    }
+
+    pub fn monitors(&self) -> &[MonitorInfo] {
+        &self.monitors
+    }
+
+    /// The smallest rect covering every monitor, which can include areas no monitor shows
+    pub fn bounds(&self) -> Rect {
+        self.monitors
+            .iter()
+            .fold(Rect::default(), |bounds, monitor| {
+                bounds.union(&monitor.rect)
+            })
+    }
+
+    pub fn monitor(&self, id: MonitorId) -> Option<&MonitorInfo> {
+        self.monitors.iter().find(|monitor| monitor.id == id)
+    }
+
+    /// The monitor showing `point`, the first one enumerated when monitors are mirrored
+    pub fn monitor_at(&self, point: impl Into<Point>) -> Option<&MonitorInfo> {
+        let point = point.into();
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.rect.contains_point(point))
+    }
+
+    /// The monitor showing `point` and where on that monitor it is
+    pub fn to_local(&self, point: impl Into<Point>) -> Option<(&MonitorInfo, Point)> {
+        let point = point.into();
+        self.monitor_at(point)
+            .map(|monitor| (monitor, monitor.to_local(point)))
+    }
+
+    /// Convert a point on the monitor with the given id to desktop coordinates
+    pub fn to_global(&self, id: MonitorId, point: impl Into<Point>) -> Option<Point> {
+        self.monitor(id).map(|monitor| monitor.to_global(point))
+    }
+
+    /// Cut a desktop rect along monitor edges, in monitor order, leaving out areas no monitor shows
+    pub fn split_rect(&self, rect: Rect) -> Vec<MonitorPiece> {
+        self.monitors
+            .iter()
+            .filter_map(|monitor| {
+                let global = monitor.rect.intersection(&rect)?;
+                Some(MonitorPiece {
+                    monitor_id: monitor.id,
+                    global,
+                    local: monitor.rect_to_local(global),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::monitor_id;
+    use crate::prelude::HasArea;
+    use crate::prelude::SyntheticLayout;
+    use crate::prelude::SyntheticPattern;
+
+    /// A portrait monitor left of the primary and a smaller one above and to the right
+    fn desktop() -> Desktop {
+        let pattern = SyntheticPattern::Gradient;
+        SyntheticLayout::new()
+            .with_monitor("primary", Rect::new(0, 0, 1920, 1080), true, pattern)
+            .with_monitor("left", Rect::new(-1080, -400, 0, 1520), false, pattern)
+            .with_monitor("top", Rect::new(1920, -720, 3200, 0), false, pattern)
+            .desktop()
+    }
+
+    #[test]
+    fn finds_monitors_at_negative_coordinates() {
+        let desktop = desktop();
+        assert_eq!(desktop.bounds(), Rect::new(-1080, -720, 3200, 1520));
+        assert_eq!(desktop.monitor_at((0, 0)).unwrap().name, "primary");
+        assert_eq!(desktop.monitor_at((-1, 0)).unwrap().name, "left");
+        assert_eq!(desktop.monitor_at((-1080, -400)).unwrap().name, "left");
+        assert_eq!(desktop.monitor_at((1920, -1)).unwrap().name, "top");
+        // gaps in the bounds belong to no monitor
+        assert!(desktop.monitor_at((1920, 0)).is_none());
+        assert!(desktop.monitor_at((-1081, 0)).is_none());
+        assert!(Desktop::default().monitor_at((0, 0)).is_none());
+    }
+
+    #[test]
+    fn maps_points_both_ways() {
+        let desktop = desktop();
+        let (monitor, local) = desktop.to_local((-1000, 100)).unwrap();
+        assert_eq!(monitor.name, "left");
+        assert_eq!(local, Point::new(80, 500));
+        assert_eq!(
+            desktop.to_global(monitor.id, local),
+            Some(Point::new(-1000, 100))
+        );
+        let (monitor, local) = desktop.to_local((2000, -700)).unwrap();
+        assert_eq!((monitor.name.as_str(), local), ("top", Point::new(80, 20)));
+        assert_eq!(desktop.to_global(monitor_id("missing"), (0, 0)), None);
+    }
+
+    #[test]
+    fn splits_rects_across_monitors() {
+        let desktop = desktop();
+        let rect = Rect::new(-100, -100, 2000, 100);
+        let pieces = desktop.split_rect(rect);
+        assert_eq!(
+            pieces,
+            vec![
+                MonitorPiece {
+                    monitor_id: monitor_id("primary"),
+                    global: Rect::new(0, 0, 1920, 100),
+                    local: Rect::new(0, 0, 1920, 100),
+                },
+                MonitorPiece {
+                    monitor_id: monitor_id("left"),
+                    global: Rect::new(-100, -100, 0, 100),
+                    local: Rect::new(980, 300, 1080, 500),
+                },
+                MonitorPiece {
+                    monitor_id: monitor_id("top"),
+                    global: Rect::new(1920, -100, 2000, 0),
+                    local: Rect::new(0, 620, 80, 720),
+                },
+            ]
+        );
+        // the area above the primary and below the top monitor is on no monitor
+        let covered: i64 = pieces.iter().map(|piece| piece.global.area()).sum();
+        assert_eq!(covered, rect.area() - 1920 * 100 - 80 * 100);
+        assert!(desktop.split_rect(Rect::new(5000, 0, 5100, 100)).is_empty());
+    }
+}
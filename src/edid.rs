@@ -0,0 +1,637 @@
+use crate::prelude::Size;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const BLOCK_LEN: usize = 128;
+const CEA_TAG: u8 = 0x02;
+const DISPLAYID_TAG: u8 = 0x70;
+
+/// A resolution and refresh rate a monitor accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisplayMode {
+    pub width: u32,
+    /// Lines in a whole frame, so 1080 for 1080i
+    pub height: u32,
+    /// Field rate of interlaced modes, 60000 for 1080i60
+    pub refresh_millihertz: u32,
+    pub interlaced: bool,
+}
+
+impl DisplayMode {
+    pub fn refresh_hz(&self) -> f64 {
+        self.refresh_millihertz as f64 / 1000.0
+    }
+}
+
+/// What a monitor reports about itself in its EDID
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edid {
+    /// Three letter PNP id like `DEL`
+    pub manufacturer: String,
+    pub product_code: u16,
+    /// Zero when the monitor only reports a serial string
+    pub serial_number: u32,
+    pub serial: Option<String>,
+    /// The monitor's friendly name like `DELL U2415`
+    pub name: Option<String>,
+    pub manufacture_week: u8,
+    pub manufacture_year: u16,
+    /// EDID version and revision, (1, 4) for EDID 1.4
+    pub version: (u8, u8),
+    /// Size of the image in millimeters, `None` for projectors
+    pub physical_size_mm: Option<Size>,
+    /// The preferred timing, which is the panel's native resolution
+    pub native_mode: Option<DisplayMode>,
+    /// Every mode from the base block and extensions, sorted and without duplicates
+    pub modes: Vec<DisplayMode>,
+}
+
+impl Edid {
+    /// The manufacturer's name, or its PNP id for ones this crate does not know
+    pub fn vendor(&self) -> &str {
+        vendor_name(&self.manufacturer).unwrap_or(self.manufacturer.as_str())
+    }
+
+    /// The friendly name, or the manufacturer and product code like `DELA0A4` the way windows shows unnamed monitors
+    pub fn model(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{}{:04X}", self.manufacturer, self.product_code),
+        }
+    }
+
+    /// The serial string, or the serial number when there is no string
+    pub fn serial_string(&self) -> Option<String> {
+        match (&self.serial, self.serial_number) {
+            (Some(serial), _) => Some(serial.clone()),
+            (None, 0) => None,
+            (None, number) => Some(number.to_string()),
+        }
+    }
+}
+
+fn vendor_name(pnp_id: &str) -> Option<&'static str> {
+    let name = match pnp_id {
+        "ACR" => "Acer",
+        "AOC" => "AOC",
+        "APP" => "Apple",
+        "AUO" => "AU Optronics",
+        "AUS" => "ASUS",
+        "BNQ" => "BenQ",
+        "BOE" => "BOE",
+        "CMN" => "Innolux",
+        "DEL" => "Dell",
+        "ENC" => "Eizo",
+        "GSM" => "LG",
+        "HPN" | "HWP" => "HP",
+        "IVM" => "Iiyama",
+        "LEN" => "Lenovo",
+        "LGD" => "LG Display",
+        "MSI" => "MSI",
+        "NEC" => "NEC",
+        "PHL" => "Philips",
+        "SAM" | "SEC" => "Samsung",
+        "SHP" => "Sharp",
+        "SNY" => "Sony",
+        "VSC" => "ViewSonic",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Parse the base block of an EDID and any CEA-861 or DisplayID extensions after it.
+///
+/// The base block must be intact, but extensions with a bad checksum or an unknown tag are skipped
+/// since monitors with broken extensions still have a usable base block.
+pub fn parse_edid(data: &[u8]) -> WincResult<Edid> {
+    let invalid = |reason: &str| WincError::InvalidEdid(reason.to_string());
+    let base = data
+        .get(..BLOCK_LEN)
+        .ok_or_else(|| invalid("shorter than the 128 byte base block"))?;
+    if base[..8] != HEADER {
+        return Err(invalid("missing header"));
+    }
+    if !checksum_ok(base) {
+        return Err(invalid("bad base block checksum"));
+    }
+
+    let letters = u16::from_be_bytes([base[8], base[9]]);
+    let manufacturer = [10, 5, 0]
+        .iter()
+        .map(|shift| (b'A' - 1 + ((letters >> shift) & 0x1f) as u8) as char)
+        .collect();
+    let version = (base[18], base[19]);
+    let mut edid = Edid {
+        manufacturer,
+        product_code: u16::from_le_bytes([base[10], base[11]]),
+        serial_number: u32::from_le_bytes([base[12], base[13], base[14], base[15]]),
+        serial: None,
+        name: None,
+        manufacture_week: base[16],
+        manufacture_year: 1990 + base[17] as u16,
+        version,
+        physical_size_mm: None,
+        native_mode: None,
+        modes: Vec::new(),
+    };
+
+    edid.modes.extend(established_timings(&base[35..38]));
+    for timing in base[38..54].chunks_exact(2) {
+        edid.modes.extend(standard_timing(timing, version));
+    }
+    let mut dtd_size = None;
+    for descriptor in base[54..126].chunks_exact(18) {
+        if let Some((mode, size)) = detailed_timing(descriptor) {
+            // the first detailed timing is the preferred one
+            edid.native_mode.get_or_insert(mode);
+            dtd_size = dtd_size.or(size);
+            edid.modes.push(mode);
+            continue;
+        }
+        match descriptor[3] {
+            0xff => edid.serial = descriptor_text(descriptor),
+            0xfc => edid.name = descriptor_text(descriptor),
+            _ => {}
+        }
+    }
+    // detailed timings give millimeters, the base block only centimeters
+    let cm = (base[21], base[22]);
+    edid.physical_size_mm = dtd_size
+        .or_else(|| (cm.0 > 0 && cm.1 > 0).then(|| Size::new(cm.0 as i32 * 10, cm.1 as i32 * 10)));
+
+    let extensions = base[126] as usize;
+    for block in data[BLOCK_LEN..].chunks_exact(BLOCK_LEN).take(extensions) {
+        if !checksum_ok(block) {
+            continue;
+        }
+        match block[0] {
+            CEA_TAG => parse_cea(block, &mut edid),
+            DISPLAYID_TAG => parse_displayid(block, &mut edid),
+            _ => {}
+        }
+    }
+
+    edid.modes.sort();
+    edid.modes.dedup();
+    Ok(edid)
+}
+
+fn checksum_ok(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+fn mode(width: u32, height: u32, refresh_hz: u32) -> DisplayMode {
+    DisplayMode {
+        width,
+        height,
+        refresh_millihertz: refresh_hz * 1000,
+        interlaced: false,
+    }
+}
+
+/// Refresh rate in millihertz of a mode with `total` pixels per frame or field
+fn refresh_millihertz(clock_hz: u64, total: u64) -> Option<u32> {
+    (total > 0).then(|| ((clock_hz * 1000 + total / 2) / total) as u32)
+}
+
+/// The old VESA modes flagged in bytes 35 to 37
+fn established_timings(bits: &[u8]) -> impl Iterator<Item = DisplayMode> + '_ {
+    const TIMINGS: [(u32, u32, u32); 17] = [
+        (720, 400, 70),
+        (720, 400, 88),
+        (640, 480, 60),
+        (640, 480, 67),
+        (640, 480, 72),
+        (640, 480, 75),
+        (800, 600, 56),
+        (800, 600, 60),
+        (800, 600, 72),
+        (800, 600, 75),
+        (832, 624, 75),
+        (1024, 768, 87),
+        (1024, 768, 60),
+        (1024, 768, 70),
+        (1024, 768, 75),
+        (1280, 1024, 75),
+        (1152, 870, 75),
+    ];
+    TIMINGS
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| bits[i / 8] & (0x80 >> (i % 8)) != 0)
+        .map(|(i, &(width, height, hz))| DisplayMode {
+            // 1024x768 at 87 Hz is the only interlaced one
+            interlaced: i == 11,
+            ..mode(width, height, hz)
+        })
+}
+
+/// A two byte standard timing, unused slots are 0x0101
+fn standard_timing(timing: &[u8], version: (u8, u8)) -> Option<DisplayMode> {
+    if timing == [0x01, 0x01] || timing[0] == 0 {
+        return None;
+    }
+    let width = (timing[0] as u32 + 31) * 8;
+    let height = match timing[1] >> 6 {
+        // 16:10 since EDID 1.3, square before
+        0 if version < (1, 3) => width,
+        0 => width * 10 / 16,
+        1 => width * 3 / 4,
+        2 => width * 4 / 5,
+        _ => width * 9 / 16,
+    };
+    Some(mode(width, height, (timing[1] & 0x3f) as u32 + 60))
+}
+
+/// An 18 byte detailed timing descriptor and the image size in millimeters it reports.
+///
+/// Display descriptors like the monitor name share the slot and start with a zero pixel clock.
+fn detailed_timing(descriptor: &[u8]) -> Option<(DisplayMode, Option<Size>)> {
+    let clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as u64 * 10_000;
+    if clock == 0 {
+        return None;
+    }
+    let high = |byte: u8, shift: u8| ((byte >> shift) & 0x0f) as u32;
+    let h_active = descriptor[2] as u32 | high(descriptor[4], 4) << 8;
+    let h_blank = descriptor[3] as u32 | high(descriptor[4], 0) << 8;
+    let v_active = descriptor[5] as u32 | high(descriptor[7], 4) << 8;
+    let v_blank = descriptor[6] as u32 | high(descriptor[7], 0) << 8;
+    let interlaced = descriptor[17] & 0x80 != 0;
+    if h_active == 0 || v_active == 0 {
+        return None;
+    }
+    // interlaced timings describe one field, which is half the frame
+    let mode = DisplayMode {
+        width: h_active,
+        height: if interlaced { v_active * 2 } else { v_active },
+        refresh_millihertz: refresh_millihertz(
+            clock,
+            (h_active + h_blank) as u64 * (v_active + v_blank) as u64,
+        )?,
+        interlaced,
+    };
+    let width_mm = descriptor[12] as i32 | (high(descriptor[14], 4) as i32) << 8;
+    let height_mm = descriptor[13] as i32 | (high(descriptor[14], 0) as i32) << 8;
+    let size = (width_mm > 0 && height_mm > 0).then(|| Size::new(width_mm, height_mm));
+    Some((mode, size))
+}
+
+/// Text of a display descriptor, which ends at a newline and is padded with spaces
+fn descriptor_text(descriptor: &[u8]) -> Option<String> {
+    let text = &descriptor[5..18];
+    let end = text.iter().position(|&c| c == b'\n').unwrap_or(text.len());
+    decode_text(&text[..end])
+}
+
+fn decode_text(text: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(text);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The CEA-861 video identification codes monitors commonly list, with nominal refresh rates
+fn cea_vic_mode(vic: u8) -> Option<DisplayMode> {
+    let (width, height, hz, interlaced) = match vic {
+        1 => (640, 480, 60, false),
+        2 | 3 => (720, 480, 60, false),
+        4 => (1280, 720, 60, false),
+        5 => (1920, 1080, 60, true),
+        16 => (1920, 1080, 60, false),
+        17 | 18 => (720, 576, 50, false),
+        19 => (1280, 720, 50, false),
+        20 => (1920, 1080, 50, true),
+        31 => (1920, 1080, 50, false),
+        32 => (1920, 1080, 24, false),
+        33 => (1920, 1080, 25, false),
+        34 => (1920, 1080, 30, false),
+        93 => (3840, 2160, 24, false),
+        94 => (3840, 2160, 25, false),
+        95 => (3840, 2160, 30, false),
+        96 => (3840, 2160, 50, false),
+        97 => (3840, 2160, 60, false),
+        98 => (4096, 2160, 24, false),
+        99 => (4096, 2160, 25, false),
+        100 => (4096, 2160, 30, false),
+        101 => (4096, 2160, 50, false),
+        102 => (4096, 2160, 60, false),
+        _ => return None,
+    };
+    Some(DisplayMode {
+        interlaced,
+        ..mode(width, height, hz)
+    })
+}
+
+/// A CEA-861 extension, which TVs and HDMI monitors use to list video formats
+fn parse_cea(block: &[u8], edid: &mut Edid) {
+    // byte 2 is where detailed timings start, data blocks fill the bytes before it
+    let dtd_start = (block[2] as usize).clamp(4, BLOCK_LEN - 1);
+    let mut offset = 4;
+    let mut native_vic = None;
+    while offset < dtd_start {
+        let header = block[offset];
+        let end = (offset + 1 + (header & 0x1f) as usize).min(dtd_start);
+        // tag 2 is the video data block, a list of short video descriptors
+        if header >> 5 == 2 {
+            for &svd in &block[offset + 1..end] {
+                // codes 1 to 64 use the top bit to flag the native format
+                let (vic, native) = match svd {
+                    129..=192 => (svd & 0x7f, true),
+                    _ => (svd, false),
+                };
+                if let Some(mode) = cea_vic_mode(vic) {
+                    if native {
+                        native_vic.get_or_insert(mode);
+                    }
+                    edid.modes.push(mode);
+                }
+            }
+        }
+        offset = end;
+    }
+    if block[2] >= 4 {
+        for descriptor in block[dtd_start..BLOCK_LEN - 1].chunks_exact(18) {
+            let Some((mode, _)) = detailed_timing(descriptor) else {
+                break;
+            };
+            edid.modes.push(mode);
+        }
+    }
+    if edid.native_mode.is_none() {
+        edid.native_mode = native_vic;
+    }
+}
+
+/// A DisplayID 1.x or 2.0 extension, which newer panels use for timings the base block cannot describe
+fn parse_displayid(block: &[u8], edid: &mut Edid) {
+    let end = (5 + block[2] as usize).min(BLOCK_LEN - 1);
+    let mut offset = 5;
+    while offset + 3 <= end {
+        let tag = block[offset];
+        let len = block[offset + 2] as usize;
+        let Some(payload) = block.get(offset + 3..offset + 3 + len) else {
+            break;
+        };
+        match tag {
+            // product identification, with the name after a 12 byte header
+            0x00 | 0x20 if len >= 12 => {
+                let name_len = (payload[11] as usize).min(len - 12);
+                if edid.name.is_none() {
+                    edid.name = decode_text(&payload[12..12 + name_len]);
+                }
+            }
+            // type I timings count the pixel clock in 10 kHz, type VII in kHz
+            0x03 => displayid_timings(payload, 10_000, edid),
+            0x22 => displayid_timings(payload, 1_000, edid),
+            _ => {}
+        }
+        offset += 3 + len;
+    }
+}
+
+fn displayid_timings(payload: &[u8], clock_unit: u64, edid: &mut Edid) {
+    // every field is stored as one less than its value
+    let field =
+        |timing: &[u8], at: usize| u16::from_le_bytes([timing[at], timing[at + 1]]) as u32 + 1;
+    for timing in payload.chunks_exact(20) {
+        let clock =
+            (u32::from_le_bytes([timing[0], timing[1], timing[2], 0]) as u64 + 1) * clock_unit;
+        let preferred = timing[3] & 0x80 != 0;
+        let interlaced = timing[3] & 0x10 != 0;
+        let (h_active, h_blank) = (field(timing, 4), field(timing, 6));
+        let (v_active, v_blank) = (field(timing, 12), field(timing, 14));
+        let Some(refresh_millihertz) = refresh_millihertz(
+            clock,
+            (h_active + h_blank) as u64 * (v_active + v_blank) as u64,
+        ) else {
+            continue;
+        };
+        // unlike detailed timings, the vertical fields already count whole frames
+        let mode = DisplayMode {
+            width: h_active,
+            height: v_active,
+            refresh_millihertz,
+            interlaced,
+        };
+        if preferred && edid.native_mode.is_none() {
+            edid.native_mode = Some(mode);
+        }
+        edid.modes.push(mode);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const DELL_U2415: &[u8] = include_bytes!("../fixtures/edid/dell_u2415.bin");
+    const DELL_U2415_BAD_EXTENSION: &[u8] =
+        include_bytes!("../fixtures/edid/dell_u2415_bad_extension.bin");
+    const LG_ULTRAFINE_4K: &[u8] = include_bytes!("../fixtures/edid/lg_ultrafine_4k.bin");
+    const SAMSUNG_CRT_V13: &[u8] = include_bytes!("../fixtures/edid/samsung_crt_v13.bin");
+    const SONY_PROJECTOR_INTERLACED: &[u8] =
+        include_bytes!("../fixtures/edid/sony_projector_interlaced.bin");
+    const BOE_DISPLAYID2: &[u8] = include_bytes!("../fixtures/edid/boe_displayid2.bin");
+    const DELL_U2415_TRUNCATED: &[u8] = include_bytes!("../fixtures/edid/dell_u2415_truncated.bin");
+    const LG_ULTRAFINE_4K_TRUNCATED_BASE: &[u8] =
+        include_bytes!("../fixtures/edid/lg_ultrafine_4k_truncated_base.bin");
+    const SAMSUNG_CRT_V13_BAD_CHECKSUM: &[u8] =
+        include_bytes!("../fixtures/edid/samsung_crt_v13_bad_checksum.bin");
+
+    const FIXTURES: [&[u8]; 6] = [
+        DELL_U2415,
+        DELL_U2415_BAD_EXTENSION,
+        LG_ULTRAFINE_4K,
+        SAMSUNG_CRT_V13,
+        SONY_PROJECTOR_INTERLACED,
+        BOE_DISPLAYID2,
+    ];
+
+    fn at(width: u32, height: u32, refresh_millihertz: u32) -> DisplayMode {
+        DisplayMode {
+            width,
+            height,
+            refresh_millihertz,
+            interlaced: false,
+        }
+    }
+
+    fn interlaced(width: u32, height: u32, refresh_millihertz: u32) -> DisplayMode {
+        DisplayMode {
+            interlaced: true,
+            ..at(width, height, refresh_millihertz)
+        }
+    }
+
+    #[test]
+    fn office_monitor_with_cea_extension() {
+        let edid = parse_edid(DELL_U2415).unwrap();
+        assert_eq!(edid.manufacturer, "DEL");
+        assert_eq!(edid.vendor(), "Dell");
+        assert_eq!(edid.product_code, 0xa0a4);
+        assert_eq!(edid.name.as_deref(), Some("DELL U2415"));
+        assert_eq!(edid.model(), "DELL U2415");
+        assert_eq!(edid.serial_string().as_deref(), Some("7MT0151T0CEL"));
+        assert_eq!(edid.serial_number, 0x4c4c3032);
+        assert_eq!((edid.manufacture_week, edid.manufacture_year), (12, 2019));
+        assert_eq!(edid.version, (1, 4));
+        assert_eq!(edid.physical_size_mm, Some(Size::new(518, 324)));
+        assert_eq!(edid.native_mode, Some(at(1920, 1200, 59950)));
+        assert_eq!(
+            edid.modes,
+            vec![
+                at(640, 480, 60000),
+                at(720, 480, 60000),
+                at(800, 600, 60000),
+                at(1024, 768, 60000),
+                at(1280, 720, 50000),
+                at(1280, 720, 60000),
+                at(1280, 800, 60000),
+                at(1280, 1024, 60000),
+                at(1600, 1000, 60000),
+                at(1680, 1050, 60000),
+                at(1920, 1080, 50000),
+                at(1920, 1080, 60000),
+                at(1920, 1200, 59950),
+            ]
+        );
+    }
+
+    #[test]
+    fn corrupt_extension_is_skipped() {
+        let edid = parse_edid(DELL_U2415_BAD_EXTENSION).unwrap();
+        let intact = parse_edid(DELL_U2415).unwrap();
+        assert_eq!(edid.name, intact.name);
+        assert_eq!(edid.native_mode, intact.native_mode);
+        // only the extension lists 1080p
+        assert!(!edid.modes.contains(&at(1920, 1080, 60000)));
+        assert_eq!(edid.modes.len(), intact.modes.len() - 5);
+    }
+
+    #[test]
+    fn displayid_adds_modes_but_base_block_stays_native() {
+        let edid = parse_edid(LG_ULTRAFINE_4K).unwrap();
+        assert_eq!(edid.vendor(), "LG");
+        // the base block name wins over the longer one in the product block
+        assert_eq!(edid.model(), "LG UltraFine");
+        assert_eq!(edid.serial_string().as_deref(), Some("104NTABC1234"));
+        assert_eq!(edid.physical_size_mm, Some(Size::new(597, 336)));
+        assert_eq!(edid.native_mode, Some(at(3840, 2160, 59997)));
+        assert!(edid.modes.contains(&at(3840, 2160, 47997)));
+        assert!(edid.modes.contains(&at(4096, 2304, 60002)));
+        assert!(edid.modes.contains(&at(1600, 900, 60000)));
+    }
+
+    #[test]
+    fn old_monitor_with_standard_timings() {
+        let edid = parse_edid(SAMSUNG_CRT_V13).unwrap();
+        assert_eq!(edid.version, (1, 3));
+        assert_eq!(edid.vendor(), "Samsung");
+        assert_eq!(edid.name, None);
+        assert_eq!(edid.model(), "SAM02A8");
+        // no serial string, so the number is used
+        assert_eq!(edid.serial_string().as_deref(), Some("1212373303"));
+        assert_eq!((edid.manufacture_week, edid.manufacture_year), (20, 2005));
+        assert_eq!(edid.physical_size_mm, Some(Size::new(338, 270)));
+        assert_eq!(edid.native_mode, Some(at(1280, 1024, 60020)));
+        assert_eq!(
+            edid.modes,
+            vec![
+                at(640, 480, 60000),
+                at(1152, 864, 75000),
+                at(1280, 1024, 60000),
+                at(1280, 1024, 60020),
+                at(1280, 1024, 75000),
+                at(1440, 900, 60000),
+            ]
+        );
+    }
+
+    #[test]
+    fn projector_prefers_interlaced() {
+        let edid = parse_edid(SONY_PROJECTOR_INTERLACED).unwrap();
+        assert_eq!(edid.model(), "VPL-HW30");
+        assert_eq!(edid.serial_string(), None);
+        // projectors have no fixed image size
+        assert_eq!(edid.physical_size_mm, None);
+        assert_eq!(edid.native_mode, Some(interlaced(1920, 1080, 60053)));
+        assert!(edid.modes.contains(&interlaced(1920, 1080, 60000)));
+        assert!(edid.modes.contains(&interlaced(1920, 1080, 50000)));
+        assert!(edid.modes.contains(&at(1920, 1080, 60000)));
+    }
+
+    #[test]
+    fn panel_with_only_displayid_timings() {
+        let edid = parse_edid(BOE_DISPLAYID2).unwrap();
+        assert_eq!(edid.vendor(), "BOE");
+        assert_eq!(edid.model(), "NE135FBM-N41");
+        assert_eq!(edid.serial_string(), None);
+        // no detailed timing, so the size comes from the centimeters in the base block
+        assert_eq!(edid.physical_size_mm, Some(Size::new(300, 190)));
+        assert_eq!(edid.native_mode, Some(at(2560, 1600, 120001)));
+        assert_eq!(edid.modes, vec![at(2560, 1600, 120001)]);
+    }
+
+    #[test]
+    fn rejects_broken_base_blocks() {
+        let is_invalid = |data: &[u8]| matches!(parse_edid(data), Err(WincError::InvalidEdid(_)));
+        assert!(is_invalid(&[]));
+        assert!(is_invalid(LG_ULTRAFINE_4K_TRUNCATED_BASE));
+        assert!(is_invalid(&DELL_U2415[..127]));
+        let mut bad_header = DELL_U2415.to_vec();
+        bad_header[0] = 0xff;
+        assert!(is_invalid(&bad_header));
+        assert!(is_invalid(SAMSUNG_CRT_V13_BAD_CHECKSUM));
+    }
+
+    #[test]
+    fn missing_extension_is_not_an_error() {
+        // the base block still announces the CEA extension the read cut off
+        assert_eq!(DELL_U2415_TRUNCATED[126], 1);
+        let edid = parse_edid(DELL_U2415_TRUNCATED).unwrap();
+        let full = parse_edid(DELL_U2415).unwrap();
+        assert_eq!(edid.model(), full.model());
+        assert_eq!(edid.serial_string(), full.serial_string());
+        assert_eq!(edid.native_mode, full.native_mode);
+    }
+
+    /// Fix the checksums of a mutated EDID so the mutation reaches the parser instead of being rejected up front
+    fn fix_checksums(data: &mut [u8]) {
+        for block in data.chunks_exact_mut(BLOCK_LEN) {
+            let sum = block[..BLOCK_LEN - 1]
+                .iter()
+                .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+            block[BLOCK_LEN - 1] = sum.wrapping_neg();
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn random_bytes_never_panic(data in proptest::collection::vec(any::<u8>(), 0..600)) {
+            let _ = parse_edid(&data);
+        }
+
+        #[test]
+        fn mutated_fixtures_never_panic(
+            fixture in 0..FIXTURES.len(),
+            edits in proptest::collection::vec((any::<usize>(), any::<u8>()), 1..40),
+            extensions in 0u8..4,
+        ) {
+            let mut data = FIXTURES[fixture].to_vec();
+            for (at, value) in edits {
+                let len = data.len();
+                data[at % len] = value;
+            }
+            data[..8].copy_from_slice(&HEADER);
+            data[126] = extensions;
+            fix_checksums(&mut data);
+            let edid = parse_edid(&data).unwrap();
+            prop_assert!(edid.modes.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+}
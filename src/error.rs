@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::prelude::Rect;
+use crate::prelude::Size;
+
+/// Everything that can go wrong while enumerating monitors or capturing them
+#[derive(Debug)]
+pub enum WincError {
+    /// The requested region is not inside the area the backend can capture from
+    RegionOutOfBounds { region: Rect, bounds: Rect },
+    /// The monitor is no longer attached, the layout should be enumerated again
+    MonitorDisconnected { name: String },
+    /// A pixel buffer did not have the size the region requires
+    SizeMismatch { expected: usize, actual: usize },
+    /// A frame did not have the width and height of the region, even if it held as many bytes
+    DimensionMismatch { expected: Size, actual: Size },
+    /// The backend reported success but produced no pixels
+    EmptyCapture,
+    /// A frame of the screen, which is always opaque, came back with alpha below 255
+    Transparency {
+        /// Pixels with alpha 0
+        transparent: usize,
+        /// Pixels with alpha between 1 and 254
+        translucent: usize,
+    },
+    /// A monitor name contains a nul and cannot be passed to the OS
+    InvalidMonitorName { name: String, nul_position: usize },
+    /// A monitor's EDID is too short, lacks the header or fails its checksum
+    InvalidEdid(String),
+    /// An outline is negative or wider than half the monitor it is drawn inside
+    InvalidOutlineWidth { width: i32, monitor: Size },
+    /// The display configuration is not one the backend knows how to capture
+    Unsupported(String),
+    /// An error from the OS or display server
+    Backend(Box<dyn Error + Send + Sync>),
+    /// Encoding a captured image failed
+    #[cfg(feature = "image")]
+    Encode(image::ImageError),
+}
+
+pub type WincResult<T> = Result<T, WincError>;
+
+impl fmt::Display for WincError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WincError::RegionOutOfBounds { region, bounds } => {
+                write!(f, "region {:?} is outside of {:?}", region, bounds)
+            }
+            WincError::MonitorDisconnected { name } => {
+                write!(f, "monitor {} is disconnected", name)
+            }
+            WincError::SizeMismatch { expected, actual } => write!(
+                f,
+                "expected a buffer of {} bytes but got {}",
+                expected, actual
+            ),
+            WincError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "expected a {}x{} frame but got {}x{}",
+                expected.width, expected.height, actual.width, actual.height
+            ),
+            WincError::EmptyCapture => write!(f, "no pixel data returned"),
+            WincError::Transparency {
+                transparent,
+                translucent,
+            } => write!(
+                f,
+                "{} transparent and {} translucent pixels in a capture of an opaque screen",
+                transparent, translucent
+            ),
+            WincError::InvalidMonitorName { name, nul_position } => write!(
+                f,
+                "monitor name {:?} has a nul character at index {}",
+                name, nul_position
+            ),
+            WincError::InvalidEdid(reason) => write!(f, "invalid EDID: {}", reason),
+            WincError::InvalidOutlineWidth { width, monitor } => write!(
+                f,
+                "an outline {} pixels wide does not fit inside a {}x{} monitor",
+                width, monitor.width, monitor.height
+            ),
+            WincError::Unsupported(reason) => write!(f, "unsupported: {}", reason),
+            WincError::Backend(e) => write!(f, "backend error: {}", e),
+            #[cfg(feature = "image")]
+            WincError::Encode(e) => write!(f, "failed to encode image: {}", e),
+        }
+    }
+}
+
+impl Error for WincError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WincError::Backend(e) => Some(e.as_ref()),
+            #[cfg(feature = "image")]
+            WincError::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for WincError {
+    fn from(e: image::ImageError) -> Self {
+        WincError::Encode(e)
+    }
+}
+
+#[cfg(windows)]
+impl From<windows::core::Error> for WincError {
+    fn from(e: windows::core::Error) -> Self {
+        WincError::Backend(Box::new(e))
+    }
+}
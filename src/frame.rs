@@ -0,0 +1,138 @@
+use std::ops::Deref;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+#[cfg(feature = "image")]
+use image::DynamicImage;
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+use crate::prelude::check_buffer_size;
+use crate::prelude::MonitorId;
+use crate::prelude::PixelFormat;
+use crate::prelude::RawImage;
+use crate::prelude::Rect;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Where and when a frame was captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameInfo {
+    /// When the capture started
+    pub timestamp: Instant,
+    /// [`MonitorInfo::id`](crate::prelude::MonitorInfo::id) of the monitor the frame came from
+    pub monitor_id: MonitorId,
+    /// The captured region, in virtual desktop coordinates
+    pub region: Rect,
+    /// Increases with every frame captured by any source, so frames from several monitors can be ordered
+    pub sequence: u64,
+}
+
+impl FrameInfo {
+    /// Stamp a frame that is about to be captured with the current time and the next sequence number
+    pub fn now(monitor_id: MonitorId, region: Rect) -> Self {
+        FrameInfo {
+            timestamp: Instant::now(),
+            monitor_id,
+            region,
+            sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Captured pixels along with where and when they came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub info: FrameInfo,
+    pub image: RawImage,
+}
+
+impl Frame {
+    pub fn new(info: FrameInfo, image: RawImage) -> Self {
+        Frame { info, image }
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.image.format
+    }
+
+    /// Bytes from the start of one row to the start of the next
+    pub fn stride(&self) -> usize {
+        self.image.width as usize * self.image.format.bytes_per_pixel()
+    }
+
+    pub fn timestamp(&self) -> Instant {
+        self.info.timestamp
+    }
+
+    pub fn monitor_id(&self) -> MonitorId {
+        self.info.monitor_id
+    }
+
+    pub fn region(&self) -> Rect {
+        self.info.region
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.info.sequence
+    }
+
+    /// Check the pixels fill the frame and, when the format has alpha, that every pixel is opaque.
+    ///
+    /// Captures with [`AlphaPolicy::Preserve`](crate::prelude::AlphaPolicy::Preserve) fail this when
+    /// the backend leaves alpha at 0, as GDI often does.
+    pub fn validate(&self) -> WincResult<()> {
+        let image = &self.image;
+        let bytes_per_pixel = image.format.bytes_per_pixel();
+        check_buffer_size(
+            image.width as usize * image.height as usize * bytes_per_pixel,
+            image.data.len(),
+        )?;
+        if !image.format.has_alpha() {
+            return Ok(());
+        }
+        let (mut transparent, mut translucent) = (0, 0);
+        for pixel in image.data.chunks_exact(bytes_per_pixel) {
+            match pixel[3] {
+                0 => transparent += 1,
+                255 => {}
+                _ => translucent += 1,
+            }
+        }
+        if transparent + translucent > 0 {
+            return Err(WincError::Transparency {
+                transparent,
+                translucent,
+            });
+        }
+        Ok(())
+    }
+
+    /// Without copying when the frame is already RGBA
+    #[cfg(feature = "image")]
+    pub fn into_rgba_image(self) -> WincResult<RgbaImage> {
+        self.image.into_rgba_image()
+    }
+
+    /// Without copying, whatever the format of the frame
+    #[cfg(feature = "image")]
+    pub fn into_dynamic_image(self) -> WincResult<DynamicImage> {
+        self.image.into_dynamic_image()
+    }
+}
+
+impl Deref for Frame {
+    type Target = RawImage;
+    fn deref(&self) -> &RawImage {
+        &self.image
+    }
+}
+
+impl From<Frame> for RawImage {
+    fn from(frame: Frame) -> Self {
+        frame.image
+    }
+}
@@ -0,0 +1,236 @@
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::prelude::convert_from_bgra;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::Metrics;
+use crate::prelude::PixelFormat;
+use crate::prelude::RawImage;
+use crate::prelude::Size;
+use crate::prelude::WincResult;
+
+/// A buffer frames can be captured into over and over without allocating.
+///
+/// Always holds 4 bytes per pixel, so backends have room to write BGRA
+/// before it is packed down to a smaller `format`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBuffer {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    data: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        FrameBuffer::from_vec(width, height, format, Vec::new())
+    }
+
+    /// Reuse an existing allocation, growing it if needed
+    pub fn from_vec(width: u32, height: u32, format: PixelFormat, mut data: Vec<u8>) -> Self {
+        data.resize(width as usize * height as usize * 4, 0);
+        FrameBuffer {
+            width,
+            height,
+            format,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width as i32, self.height as i32)
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Change the format the next capture is converted to
+    pub fn set_format(&mut self, format: PixelFormat) {
+        self.format = format;
+    }
+
+    /// Change the dimensions, keeping the allocation when it is big enough
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.data.resize(width as usize * height as usize * 4, 0);
+    }
+
+    /// The pixels of the last capture, tightly packed in [`format`](Self::format)
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len()]
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        let len = self.len();
+        &mut self.data[..len]
+    }
+
+    fn len(&self) -> usize {
+        self.width as usize * self.height as usize * self.format.bytes_per_pixel()
+    }
+
+    /// The whole buffer for a backend to write BGRA into
+    pub(crate) fn bgra_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Convert the BGRA a backend just wrote to [`format`](Self::format),
+    /// which [`AlphaPolicy::Drop`] changes to the format without alpha
+    pub(crate) fn finish_bgra(
+        &mut self,
+        alpha: AlphaPolicy,
+        metrics: &mut Metrics,
+    ) -> WincResult<()> {
+        convert_from_bgra(&mut self.data, self.format, alpha, metrics)?;
+        self.format = alpha.output_format(self.format);
+        Ok(())
+    }
+
+    /// Copy the pixels out, leaving the buffer to be reused
+    pub fn to_raw_image(&self) -> RawImage {
+        RawImage {
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            data: self.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn into_raw_image(self) -> RawImage {
+        let len = self.len();
+        let mut data = self.data;
+        data.truncate(len);
+        RawImage {
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            data,
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Buffers that are handed back when the frames using them are dropped, for sending frames to other threads
+#[derive(Debug, Clone, Default)]
+pub struct FramePool {
+    free: Arc<Mutex<Vec<Vec<u8>>>>,
+    max_free: usize,
+}
+
+impl FramePool {
+    /// Keep at most `max_free` buffers around while they are not in use
+    pub fn new(max_free: usize) -> Self {
+        FramePool {
+            free: Arc::default(),
+            max_free,
+        }
+    }
+
+    /// A buffer for a frame, reusing one from the pool when there is one
+    pub fn get(&self, width: u32, height: u32, format: PixelFormat) -> PooledFrameBuffer {
+        let data = self.free.lock().unwrap().pop().unwrap_or_default();
+        PooledFrameBuffer {
+            frame: Some(FrameBuffer::from_vec(width, height, format, data)),
+            pool: self.clone(),
+        }
+    }
+
+    /// How many buffers are waiting to be reused
+    pub fn free_count(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    fn put(&self, data: Vec<u8>) {
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.max_free {
+            free.push(data);
+        }
+    }
+}
+
+/// A [`FrameBuffer`] that goes back to its [`FramePool`] on drop
+#[derive(Debug)]
+pub struct PooledFrameBuffer {
+    frame: Option<FrameBuffer>,
+    pool: FramePool,
+}
+
+impl PooledFrameBuffer {
+    /// Keep the buffer instead of returning it to the pool
+    pub fn detach(mut self) -> FrameBuffer {
+        self.frame.take().unwrap()
+    }
+}
+
+impl Deref for PooledFrameBuffer {
+    type Target = FrameBuffer;
+    fn deref(&self) -> &FrameBuffer {
+        self.frame.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledFrameBuffer {
+    fn deref_mut(&mut self) -> &mut FrameBuffer {
+        self.frame.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledFrameBuffer {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            self.pool.put(frame.into_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_reuses_buffers() {
+        let pool = FramePool::new(2);
+        let frame = pool.get(64, 32, PixelFormat::Bgra8);
+        let ptr = frame.as_bytes().as_ptr();
+        drop(frame);
+        assert_eq!(pool.free_count(), 1);
+        let frame = pool.get(32, 32, PixelFormat::Gray8);
+        assert_eq!(frame.as_bytes().as_ptr(), ptr);
+        assert_eq!(frame.as_bytes().len(), 32 * 32);
+        assert_eq!(pool.free_count(), 0);
+        let kept = frame.detach();
+        assert_eq!(kept.size(), Size::new(32, 32));
+        assert_eq!(pool.free_count(), 0);
+    }
+
+    #[test]
+    fn pool_drops_extra_buffers() {
+        let pool = FramePool::new(1);
+        let frames: Vec<_> = (0..3).map(|_| pool.get(8, 8, PixelFormat::Rgba8)).collect();
+        drop(frames);
+        assert_eq!(pool.free_count(), 1);
+    }
+
+    #[test]
+    fn pooled_frames_cross_threads() {
+        let pool = FramePool::new(4);
+        let frame = pool.get(4, 4, PixelFormat::Rgba8);
+        std::thread::spawn(move || drop(frame)).join().unwrap();
+        assert_eq!(pool.free_count(), 1);
+    }
+}
@@ -0,0 +1,322 @@
+use std::ops::Add;
+use std::ops::AddAssign;
+use std::ops::Div;
+use std::ops::Mul;
+use std::ops::Neg;
+use std::ops::Sub;
+use std::ops::SubAssign;
+#[cfg(windows)]
+use windows::Win32::Foundation::POINT;
+#[cfg(windows)]
+use windows::Win32::Foundation::RECT;
+#[cfg(windows)]
+use windows::Win32::Foundation::SIZE;
+
+/// A position in pixels, usually in virtual desktop coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A width and height in pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// An axis aligned rectangle in pixels, with exclusive `right` and `bottom` edges like a windows `RECT`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+/// Multiply a coordinate by a scale factor, rounding to the nearest pixel with halves away from zero.
+///
+/// Converting to a factor of at least 1 and back with the inverse always gives the original coordinate.
+pub fn scale_coordinate(value: i32, factor: f64) -> i32 {
+    (value as f64 * factor).round() as i32
+}
+
+impl Point {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Point { x, y }
+    }
+
+    /// Scale both coordinates about the origin, see [`scale_coordinate`]
+    pub fn scale(&self, factor: f64) -> Self {
+        Point::new(
+            scale_coordinate(self.x, factor),
+            scale_coordinate(self.y, factor),
+        )
+    }
+}
+
+impl Size {
+    pub const fn new(width: i32, height: i32) -> Self {
+        Size { width, height }
+    }
+
+    pub fn scale(&self, factor: f64) -> Self {
+        Size::new(
+            scale_coordinate(self.width, factor),
+            scale_coordinate(self.height, factor),
+        )
+    }
+}
+
+impl Rect {
+    pub const fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn from_origin_size(origin: Point, size: Size) -> Self {
+        Rect {
+            left: origin.x,
+            top: origin.y,
+            right: origin.x + size.width,
+            bottom: origin.y + size.height,
+        }
+    }
+
+    pub fn origin(&self) -> Point {
+        Point::new(self.left, self.top)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.right - self.left, self.bottom - self.top)
+    }
+
+    /// Scale every edge about the origin, see [`scale_coordinate`].
+    ///
+    /// Edges rather than the size are rounded, so rects that touch still touch afterwards,
+    /// and the size can be 1 off from the scaled size.
+    pub fn scale(&self, factor: f64) -> Self {
+        Rect::new(
+            scale_coordinate(self.left, factor),
+            scale_coordinate(self.top, factor),
+            scale_coordinate(self.right, factor),
+            scale_coordinate(self.bottom, factor),
+        )
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Self {
+        Point { x, y }
+    }
+}
+impl From<Point> for (i32, i32) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+impl AddAssign for Point {
+    fn add_assign(&mut self, rhs: Point) {
+        *self = *self + rhs;
+    }
+}
+impl SubAssign for Point {
+    fn sub_assign(&mut self, rhs: Point) {
+        *self = *self - rhs;
+    }
+}
+impl Neg for Point {
+    type Output = Point;
+    fn neg(self) -> Point {
+        Point::new(-self.x, -self.y)
+    }
+}
+impl Add<Size> for Point {
+    type Output = Point;
+    fn add(self, rhs: Size) -> Point {
+        Point::new(self.x + rhs.width, self.y + rhs.height)
+    }
+}
+impl Sub<Size> for Point {
+    type Output = Point;
+    fn sub(self, rhs: Size) -> Point {
+        Point::new(self.x - rhs.width, self.y - rhs.height)
+    }
+}
+
+impl Add for Size {
+    type Output = Size;
+    fn add(self, rhs: Size) -> Size {
+        Size::new(self.width + rhs.width, self.height + rhs.height)
+    }
+}
+impl Sub for Size {
+    type Output = Size;
+    fn sub(self, rhs: Size) -> Size {
+        Size::new(self.width - rhs.width, self.height - rhs.height)
+    }
+}
+impl Mul<i32> for Size {
+    type Output = Size;
+    fn mul(self, rhs: i32) -> Size {
+        Size::new(self.width * rhs, self.height * rhs)
+    }
+}
+impl Div<i32> for Size {
+    type Output = Size;
+    fn div(self, rhs: i32) -> Size {
+        Size::new(self.width / rhs, self.height / rhs)
+    }
+}
+
+/// Offsetting a rect by a point moves it without changing its size
+impl Add<Point> for Rect {
+    type Output = Rect;
+    fn add(self, rhs: Point) -> Rect {
+        Rect::new(
+            self.left + rhs.x,
+            self.top + rhs.y,
+            self.right + rhs.x,
+            self.bottom + rhs.y,
+        )
+    }
+}
+impl Sub<Point> for Rect {
+    type Output = Rect;
+    fn sub(self, rhs: Point) -> Rect {
+        self + -rhs
+    }
+}
+impl AddAssign<Point> for Rect {
+    fn add_assign(&mut self, rhs: Point) {
+        *self = *self + rhs;
+    }
+}
+impl SubAssign<Point> for Rect {
+    fn sub_assign(&mut self, rhs: Point) {
+        *self = *self - rhs;
+    }
+}
+
+#[cfg(windows)]
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        Rect::new(rect.left, rect.top, rect.right, rect.bottom)
+    }
+}
+#[cfg(windows)]
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> Self {
+        RECT {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+#[cfg(windows)]
+impl From<POINT> for Point {
+    fn from(point: POINT) -> Self {
+        Point::new(point.x, point.y)
+    }
+}
+#[cfg(windows)]
+impl From<Point> for POINT {
+    fn from(point: Point) -> Self {
+        POINT {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+#[cfg(windows)]
+impl From<SIZE> for Size {
+    fn from(size: SIZE) -> Self {
+        Size::new(size.cx, size.cy)
+    }
+}
+#[cfg(windows)]
+impl From<Size> for SIZE {
+    fn from(size: Size) -> Self {
+        SIZE {
+            cx: size.width,
+            cy: size.height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_arithmetic() {
+        let mut p = Point::new(3, -4);
+        assert_eq!(p + Point::new(1, 1), Point::new(4, -3));
+        assert_eq!(p - Point::new(1, 1), Point::new(2, -5));
+        assert_eq!(-p, Point::new(-3, 4));
+        assert_eq!(p + Size::new(10, 20), Point::new(13, 16));
+        p += Point::new(-3, 4);
+        assert_eq!(p, Point::default());
+        assert_eq!(<(i32, i32)>::from(Point::from((7, 8))), (7, 8));
+    }
+
+    #[test]
+    fn size_arithmetic() {
+        let size = Size::new(1920, 1080);
+        assert_eq!(size / 2, Size::new(960, 540));
+        assert_eq!(size * 2 - size, size);
+    }
+
+    #[test]
+    fn scaling_rounds_to_nearest() {
+        assert_eq!(scale_coordinate(3, 1.5), 5);
+        assert_eq!(scale_coordinate(-3, 1.5), -5);
+        assert_eq!(scale_coordinate(5, 1.0 / 1.5), 3);
+        assert_eq!(Point::new(1, -1).scale(1.25), Point::new(1, -1));
+        assert_eq!(Size::new(1920, 1080).scale(1.5), Size::new(2880, 1620));
+        // 3 and 4 wide halves of a 7 wide rect stay touching, one of them gets the extra pixel
+        let (a, b) = (Rect::new(0, 0, 3, 1), Rect::new(3, 0, 7, 1));
+        assert_eq!(a.scale(1.5), Rect::new(0, 0, 5, 2));
+        assert_eq!(b.scale(1.5), Rect::new(5, 0, 11, 2));
+        for factor in [1.0, 1.25, 1.5, 1.75, 2.0, 2.25, 3.0, 3.5] {
+            for value in -500..500 {
+                let scaled = scale_coordinate(value, factor);
+                assert_eq!(scale_coordinate(scaled, 1.0 / factor), value, "{}", factor);
+            }
+        }
+    }
+
+    #[test]
+    fn rect_offset() {
+        let rect = Rect::from_origin_size(Point::new(-100, 50), Size::new(200, 100));
+        assert_eq!(rect, Rect::new(-100, 50, 100, 150));
+        assert_eq!(rect.size(), Size::new(200, 100));
+        let moved = rect + Point::new(100, -50);
+        assert_eq!(moved.origin(), Point::default());
+        assert_eq!(moved.size(), rect.size());
+        assert_eq!(moved - Point::new(100, -50), rect);
+    }
+}
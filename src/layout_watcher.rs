@@ -0,0 +1,312 @@
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+#[cfg(windows)]
+use crate::prelude::get_monitor_infos;
+use crate::prelude::HasTopLeft;
+use crate::prelude::MonitorId;
+use crate::prelude::MonitorIdentity;
+use crate::prelude::MonitorInfo;
+use crate::prelude::Rect;
+use crate::prelude::WincResult;
+
+/// A change between two snapshots of the monitor layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayoutEvent {
+    Added(MonitorInfo),
+    Removed(MonitorInfo),
+    /// The monitor kept its size but its top left moved
+    Moved {
+        monitor: MonitorInfo,
+        from: Rect,
+    },
+    /// The monitor's resolution or orientation changed, it may also have moved
+    Resized {
+        monitor: MonitorInfo,
+        from: Rect,
+    },
+    /// Another monitor became the primary, `None` while no monitor is attached
+    PrimaryChanged {
+        /// [`MonitorInfo::id`] of the old primary in the old layout
+        from: Option<MonitorId>,
+        /// [`MonitorInfo::id`] of the new primary in the new layout
+        to: Option<MonitorId>,
+    },
+}
+
+/// The events turning the `old` layout into the `new` one.
+///
+/// Monitors are paired by [`MonitorIdentity`], so one windows renumbered is not reported as removed and added.
+/// Removals come first, then additions, then changes to monitors in both, then a change of primary.
+pub fn diff_layouts(old: &[MonitorInfo], new: &[MonitorInfo]) -> Vec<LayoutEvent> {
+    let old_ids: Vec<MonitorIdentity> = old.iter().map(MonitorIdentity::of).collect();
+    let new_ids: Vec<MonitorIdentity> = new.iter().map(MonitorIdentity::of).collect();
+    // identical monitors without a serial or connector share an identity, so pair them in order
+    let mut paired = vec![None; old.len()];
+    let mut taken = vec![false; new.len()];
+    for (i, old_id) in old_ids.iter().enumerate() {
+        let found = (0..new.len()).find(|&j| !taken[j] && new_ids[j] == *old_id);
+        if let Some(j) = found {
+            taken[j] = true;
+            paired[i] = Some(j);
+        }
+    }
+
+    let mut events = Vec::new();
+    for (i, monitor) in old.iter().enumerate() {
+        if paired[i].is_none() {
+            events.push(LayoutEvent::Removed(monitor.clone()));
+        }
+    }
+    for (j, monitor) in new.iter().enumerate() {
+        if !taken[j] {
+            events.push(LayoutEvent::Added(monitor.clone()));
+        }
+    }
+    for (i, j) in paired.iter().enumerate() {
+        let Some(j) = *j else {
+            continue;
+        };
+        let (from, monitor) = (old[i].rect, &new[j]);
+        if monitor.rect.size() != from.size() {
+            events.push(LayoutEvent::Resized {
+                monitor: monitor.clone(),
+                from,
+            });
+        } else if monitor.rect.top_left() != from.top_left() {
+            events.push(LayoutEvent::Moved {
+                monitor: monitor.clone(),
+                from,
+            });
+        }
+    }
+
+    let old_primary = old.iter().position(|monitor| monitor.is_primary);
+    let new_primary = new.iter().position(|monitor| monitor.is_primary);
+    let same_primary = match (old_primary, new_primary) {
+        (Some(i), Some(j)) => paired[i] == Some(j),
+        (None, None) => true,
+        _ => false,
+    };
+    if !same_primary {
+        events.push(LayoutEvent::PrimaryChanged {
+            from: old_primary.map(|i| old[i].id),
+            to: new_primary.map(|j| new[j].id),
+        });
+    }
+    events
+}
+
+/// Remembers the last layout seen, for polling without a [`LayoutWatcher`] thread
+#[derive(Debug, Clone, Default)]
+pub struct LayoutTracker {
+    monitors: Vec<MonitorInfo>,
+}
+
+impl LayoutTracker {
+    pub fn new(monitors: Vec<MonitorInfo>) -> Self {
+        LayoutTracker { monitors }
+    }
+
+    pub fn monitors(&self) -> &[MonitorInfo] {
+        &self.monitors
+    }
+
+    /// Replace the remembered layout, returning what changed
+    pub fn update(&mut self, monitors: Vec<MonitorInfo>) -> Vec<LayoutEvent> {
+        let events = diff_layouts(&self.monitors, &monitors);
+        self.monitors = monitors;
+        events
+    }
+}
+
+/// Polls the monitor layout on a background thread and sends a [`LayoutEvent`] for every change.
+///
+/// The thread stops when the watcher is stopped or dropped.
+/// Dropping only the receiver stops it at the next change, since sending an event is the only way to notice,
+/// so a layout that never changes keeps it polling until the watcher goes too.
+pub struct LayoutWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Start watching the layout `snapshot` returns, taking a snapshot every `interval`.
+///
+/// The first snapshot is taken before returning and is the baseline, so it produces no events.
+/// Later snapshots that fail are skipped, since enumeration can fail while monitors are being re-arranged.
+pub fn watch_layout<F>(
+    mut snapshot: F,
+    interval: Duration,
+) -> WincResult<(LayoutWatcher, Receiver<LayoutEvent>)>
+where
+    F: FnMut() -> WincResult<Vec<MonitorInfo>> + Send + 'static,
+{
+    let mut tracker = LayoutTracker::new(snapshot()?);
+    let (event_sender, events) = mpsc::channel();
+    let (stop, stop_receiver) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || loop {
+        match stop_receiver.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => {}
+            // a stop message or the watcher being dropped
+            _ => return,
+        }
+        let Ok(monitors) = snapshot() else {
+            continue;
+        };
+        for event in tracker.update(monitors) {
+            if event_sender.send(event).is_err() {
+                return;
+            }
+        }
+    });
+    Ok((
+        LayoutWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        },
+        events,
+    ))
+}
+
+/// Watch the monitors attached to this machine, see [`watch_layout`]
+#[cfg(windows)]
+pub fn watch_monitor_layout(
+    interval: Duration,
+) -> WincResult<(LayoutWatcher, Receiver<LayoutEvent>)> {
+    watch_layout(get_monitor_infos, interval)
+}
+
+impl LayoutWatcher {
+    /// Stop polling and wait for the thread to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // dropping the sender wakes the thread up
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for LayoutWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::SyntheticLayout;
+    use crate::prelude::SyntheticPattern;
+
+    const PATTERN: SyntheticPattern = SyntheticPattern::Gradient;
+
+    #[test]
+    fn unchanged_layout_has_no_events() {
+        let layout = SyntheticLayout::new()
+            .with_monitor("a", Rect::new(0, 0, 1920, 1080), true, PATTERN)
+            .with_monitor("b", Rect::new(1920, 0, 3840, 1080), false, PATTERN)
+            .snapshot();
+        assert_eq!(diff_layouts(&layout, &layout), vec![]);
+        assert_eq!(diff_layouts(&[], &[]), vec![]);
+    }
+
+    #[test]
+    fn reports_every_kind_of_change() {
+        let old = SyntheticLayout::new()
+            .with_monitor("a", Rect::new(0, 0, 1920, 1080), true, PATTERN)
+            .with_monitor("b", Rect::new(1920, 0, 3840, 1080), false, PATTERN)
+            .with_monitor("c", Rect::new(-1280, 0, 0, 1024), false, PATTERN)
+            .snapshot();
+        let new = SyntheticLayout::new()
+            // rotated to portrait
+            .with_monitor("a", Rect::new(0, 0, 1080, 1920), false, PATTERN)
+            .with_monitor("b", Rect::new(1080, 0, 3000, 1080), true, PATTERN)
+            .with_monitor("d", Rect::new(3000, 0, 5560, 1440), false, PATTERN)
+            .snapshot();
+        assert_eq!(
+            diff_layouts(&old, &new),
+            vec![
+                LayoutEvent::Removed(old[2].clone()),
+                LayoutEvent::Added(new[2].clone()),
+                LayoutEvent::Resized {
+                    monitor: new[0].clone(),
+                    from: old[0].rect,
+                },
+                LayoutEvent::Moved {
+                    monitor: new[1].clone(),
+                    from: old[1].rect,
+                },
+                LayoutEvent::PrimaryChanged {
+                    from: Some(old[0].id),
+                    to: Some(new[1].id),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unplugging_everything_loses_the_primary() {
+        let old = SyntheticLayout::new()
+            .with_monitor("a", Rect::new(0, 0, 1920, 1080), true, PATTERN)
+            .snapshot();
+        let mut tracker = LayoutTracker::new(old.clone());
+        assert_eq!(
+            tracker.update(vec![]),
+            vec![
+                LayoutEvent::Removed(old[0].clone()),
+                LayoutEvent::PrimaryChanged {
+                    from: Some(old[0].id),
+                    to: None,
+                },
+            ]
+        );
+        assert!(tracker.monitors().is_empty());
+        assert_eq!(tracker.update(old.clone()).len(), 2);
+        assert_eq!(tracker.update(old), vec![]);
+    }
+
+    #[test]
+    fn dropping_the_receiver_stops_the_thread_at_the_next_change() {
+        let layouts = [
+            SyntheticLayout::dual_1080p(PATTERN).snapshot(),
+            SyntheticLayout::new()
+                .with_monitor("a", Rect::new(0, 0, 1920, 1080), true, PATTERN)
+                .snapshot(),
+        ];
+        let (done, finished) = mpsc::channel();
+        let mut polls = 0;
+        let snapshot = move || {
+            polls += 1;
+            // the layout changes on the third poll, after the receiver is gone
+            let _ = done.send(());
+            Ok(layouts[usize::from(polls >= 3)].clone())
+        };
+        let (watcher, events) = watch_layout(snapshot, Duration::from_millis(1)).unwrap();
+        drop(events);
+        let timeout = Duration::from_secs(5);
+        for _ in 0..3 {
+            finished.recv_timeout(timeout).unwrap();
+        }
+        // the thread returns after failing to send, dropping the snapshot closure on its way out
+        while finished.recv_timeout(timeout).is_ok() {}
+        let thread = watcher.thread.as_ref().unwrap();
+        for _ in 0..5000 {
+            if thread.is_finished() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("the thread kept polling after the receiver was dropped");
+    }
+}
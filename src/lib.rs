@@ -1,17 +1,61 @@
+mod capture_source;
+#[cfg(feature = "bgra_to_rgba")]
+mod convert;
+mod desktop;
+mod edid;
+mod error;
+mod frame;
+mod frame_buffer;
+mod geometry;
+mod layout_watcher;
 mod metrics;
+#[cfg(windows)]
 mod monitor;
+mod monitor_identity;
 mod monitor_info;
+#[cfg(windows)]
 mod monitor_region_capturer;
+mod monitor_set;
+mod raw_image;
 mod rect_extensions;
+mod resize;
+#[cfg(feature = "bgra_to_rgba")]
 mod shuffle;
+mod stitched_capturer;
+mod synthetic;
+#[cfg(test)]
 mod tests;
+mod virtual_screen;
+#[cfg(all(unix, feature = "x11"))]
+mod x11;
 
 pub mod prelude {
+    pub use crate::capture_source::*;
+    #[cfg(feature = "bgra_to_rgba")]
+    pub use crate::convert::*;
+    pub use crate::desktop::*;
+    pub use crate::edid::*;
+    pub use crate::error::*;
+    pub use crate::frame::*;
+    pub use crate::frame_buffer::*;
+    pub use crate::geometry::*;
+    pub use crate::layout_watcher::*;
     pub use crate::metrics::*;
+    #[cfg(windows)]
     pub use crate::monitor::*;
+    pub use crate::monitor_identity::*;
     pub use crate::monitor_info::*;
+    #[cfg(windows)]
     pub use crate::monitor_region_capturer::*;
+    pub use crate::monitor_set::*;
+    pub use crate::raw_image::*;
     pub use crate::rect_extensions::*;
+    pub use crate::resize::*;
+    #[cfg(feature = "bgra_to_rgba")]
     pub use crate::shuffle::*;
-    pub use windows::Win32::Foundation::RECT;
+    pub use crate::stitched_capturer::*;
+    pub use crate::synthetic::*;
+    pub use crate::virtual_screen::*;
+    #[cfg(all(unix, feature = "x11"))]
+    pub use crate::x11::*;
 }
@@ -1,13 +1,18 @@
+#[cfg(feature = "metrics")]
 use indexmap::IndexMap;
 use std::time::Duration;
+#[cfg(feature = "metrics")]
 use std::time::Instant;
 
-#[derive(Debug, Clone)]
+/// One completed timing, serialized as `{"name": "capture", "duration": {"secs": 0, "nanos": 1500000}}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Metric {
     pub name: String,
     pub duration: Duration,
 }
 
+#[cfg(feature = "metrics")]
 #[derive(Default, Debug)]
 pub enum Metrics {
     #[default]
@@ -18,6 +23,46 @@ pub enum Metrics {
     },
 }
 
+/// Without the `metrics` feature there is nothing to record, so every call compiles away
+#[cfg(not(feature = "metrics"))]
+#[derive(Default, Debug)]
+pub enum Metrics {
+    #[default]
+    None,
+}
+
+#[cfg(not(feature = "metrics"))]
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::None
+    }
+
+    #[inline(always)]
+    pub fn begin(&mut self, _name: &str) {}
+
+    #[inline(always)]
+    pub fn end(&mut self, _name: &str) {}
+
+    #[allow(dead_code)]
+    pub fn report(&self) -> String {
+        "Metrics disabled, nothing to report".to_string()
+    }
+
+    pub fn total(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    pub fn completed(&self) -> Vec<Metric> {
+        Vec::new()
+    }
+
+    /// Nothing is kept without the `metrics` feature
+    pub fn from_completed(_completed: impl IntoIterator<Item = Metric>) -> Self {
+        Metrics::None
+    }
+}
+
+#[cfg(feature = "metrics")]
 impl Metrics {
     pub fn new() -> Self {
         Metrics::Some {
@@ -70,6 +115,50 @@ impl Metrics {
             Metrics::Some { completed, .. } => completed.values().sum(),
         }
     }
+
+    /// The finished timings in the order they finished
+    pub fn completed(&self) -> Vec<Metric> {
+        match self {
+            Metrics::None => Vec::new(),
+            Metrics::Some { completed, .. } => completed
+                .iter()
+                .map(|(name, duration)| Metric {
+                    name: name.clone(),
+                    duration: *duration,
+                })
+                .collect(),
+        }
+    }
+
+    /// Recording metrics that already hold `completed`, like ones loaded from a report
+    pub fn from_completed(completed: impl IntoIterator<Item = Metric>) -> Self {
+        Metrics::Some {
+            ongoing: Default::default(),
+            completed: completed
+                .into_iter()
+                .map(|metric| (metric.name, metric.duration))
+                .collect(),
+        }
+    }
+}
+
+/// Serialized as the list of [`Metric`]s from [`Metrics::completed`], timings still running are left out.
+///
+/// Needs the `metrics` feature too, without it there would be nothing to write and loaded timings would be dropped.
+#[cfg(all(feature = "serde", feature = "metrics"))]
+impl serde::Serialize for Metrics {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.completed())
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "metrics"))]
+impl<'de> serde::Deserialize<'de> for Metrics {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Metrics::from_completed(Vec::<Metric>::deserialize(
+            deserializer,
+        )?))
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +166,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "metrics")]
     fn test_metrics_tracking() {
         let mut metrics = Metrics::new();
 
@@ -93,4 +183,13 @@ mod tests {
         assert!(report.contains("z first"));
         assert!(report.contains("a second"));
     }
+
+    #[test]
+    #[cfg(not(feature = "metrics"))]
+    fn test_metrics_disabled() {
+        let mut metrics = Metrics::new();
+        metrics.begin("first");
+        metrics.end("first");
+        assert_eq!(metrics.total(), Duration::ZERO);
+    }
 }
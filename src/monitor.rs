@@ -1,35 +1,74 @@
+use std::mem;
 use std::ptr;
 use widestring::U16CString;
+use windows::core::w;
 use windows::core::Result;
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::E_INVALIDARG;
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::RECT;
 use windows::Win32::Graphics::Gdi::CreateDCW;
+use windows::Win32::Graphics::Gdi::EnumDisplayDevicesW;
+use windows::Win32::Graphics::Gdi::EnumDisplayMonitors;
+use windows::Win32::Graphics::Gdi::GetMonitorInfoW;
+use windows::Win32::Graphics::Gdi::DISPLAY_DEVICEW;
 use windows::Win32::Graphics::Gdi::HDC;
+use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::Graphics::Gdi::MONITORINFOEXW;
+use windows::Win32::System::Registry::RegGetValueW;
+use windows::Win32::System::Registry::HKEY_LOCAL_MACHINE;
+use windows::Win32::System::Registry::RRF_RT_REG_BINARY;
+use windows::Win32::UI::HiDpi::GetDpiForMonitor;
+use windows::Win32::UI::HiDpi::MDT_EFFECTIVE_DPI;
 
-use crate::prelude::get_monitor_infos;
+use crate::prelude::monitor_id;
+use crate::prelude::parse_edid;
+use crate::prelude::Edid;
+use crate::prelude::HasMonitorInfo;
 use crate::prelude::MonitorInfo;
+use crate::prelude::MonitorSet;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+use crate::prelude::BASE_DPI;
 
 pub struct Monitor {
     pub info: MonitorInfo,
     pub device_context: HDC,
 }
 
-pub fn get_all_monitors() -> Result<Vec<Monitor>> {
+impl HasMonitorInfo for Monitor {
+    fn info(&self) -> &MonitorInfo {
+        &self.info
+    }
+}
+
+/// Same as [`get_all_monitors`], but with lookups like [`MonitorSet::primary`]
+pub fn get_monitor_set() -> WincResult<MonitorSet<Monitor>> {
+    Ok(MonitorSet::new(get_all_monitors()?))
+}
+
+pub fn get_all_monitors() -> WincResult<Vec<Monitor>> {
     let monitor_infos = get_monitor_infos()?;
     let mut monitors = Vec::new();
 
     for monitor_info in monitor_infos {
         // intermediate variables are required to ensure the pointer contents remain in scope
         let a = U16CString::from_str(&monitor_info.name).map_err(|e| {
-            windows::core::Error::new(
-                E_INVALIDARG,
-                format!("Invalid null character at index {}", e.nul_position()),
-            )
+            WincError::InvalidMonitorName {
+                name: monitor_info.name.clone(),
+                nul_position: e.nul_position(),
+            }
         })?;
         let b = a.as_ptr();
         let name_pcwstr = PCWSTR(b);
         let device_context =
             unsafe { CreateDCW(name_pcwstr, name_pcwstr, PCWSTR(ptr::null()), None) };
+        // the device can vanish between enumeration and opening it
+        if device_context.is_invalid() {
+            return Err(WincError::MonitorDisconnected {
+                name: monitor_info.name,
+            });
+        }
 
         monitors.push(Monitor {
             info: monitor_info,
@@ -39,3 +78,153 @@ pub fn get_all_monitors() -> Result<Vec<Monitor>> {
 
     Ok(monitors)
 }
+
+pub fn get_monitor_infos() -> WincResult<Vec<MonitorInfo>> {
+    // box it up so we can pass it to the callback
+    let results: *mut Vec<(HMONITOR, MONITORINFOEXW)> = Box::into_raw(Box::default());
+
+    // use proc method to iterate monitors and collect into results vec
+    unsafe {
+        EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(monitor_enum_proc),
+            LPARAM(results as isize),
+        )
+        .ok()?;
+    };
+
+    // convert results back into a vec
+    let results = unsafe { &Box::from_raw(results) };
+
+    // convert vec of MONITORINFOEXW into vec of MonitorInfo
+    let results = results
+        .iter()
+        .map(|(h_monitor, info)| {
+            let sz_device_ptr = info.szDevice.as_ptr();
+            let sz_device_string =
+                unsafe { U16CString::from_ptr_str(sz_device_ptr).to_string_lossy() };
+            let device_id = get_monitor_device_id(&sz_device_string);
+            MonitorInfo {
+                id: monitor_id(&sz_device_string),
+                name: sz_device_string,
+                rect: info.monitorInfo.rcMonitor.into(),
+                work_area: info.monitorInfo.rcWork.into(),
+                is_primary: info.monitorInfo.dwFlags == 1,
+                dpi: get_monitor_dpi(*h_monitor),
+                connector: device_id
+                    .as_deref()
+                    .and_then(split_device_id)
+                    .map(|(_, _, instance)| instance.to_string()),
+                edid: device_id.as_deref().and_then(get_monitor_edid),
+            }
+        })
+        .collect::<Vec<MonitorInfo>>();
+    Ok(results)
+}
+
+extern "system" fn monitor_enum_proc(
+    h_monitor: HMONITOR,
+    _: HDC,
+    _: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    let results = unsafe { Box::from_raw(data.0 as *mut Vec<(HMONITOR, MONITORINFOEXW)>) };
+    let results = Box::leak(results);
+
+    match get_monitor_info_exw(h_monitor) {
+        Ok(monitor_info_exw) => {
+            results.push((h_monitor, monitor_info_exw));
+            BOOL::from(true)
+        }
+        Err(_) => BOOL::from(false),
+    }
+}
+
+/// The effective DPI the user picked in display settings, or [`BASE_DPI`] before Windows 8.1.
+///
+/// Processes that are not per-monitor DPI aware are told every monitor is at [`BASE_DPI`].
+fn get_monitor_dpi(h_monitor: HMONITOR) -> u32 {
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    match unsafe { GetDpiForMonitor(h_monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) } {
+        // x and y are always the same
+        Ok(()) if dpi_x > 0 => dpi_x,
+        _ => BASE_DPI,
+    }
+}
+
+/// Makes `EnumDisplayDevicesW` report the device interface path, defined in WindowsAndMessaging which we do not otherwise need
+const EDD_GET_DEVICE_INTERFACE_NAME: u32 = 1;
+
+/// The device interface path of the monitor attached to an adapter like `\\.\DISPLAY1`
+fn get_monitor_device_id(device_name: &str) -> Option<String> {
+    let device_name = U16CString::from_str(device_name).ok()?;
+    let mut device: DISPLAY_DEVICEW = unsafe { mem::zeroed() };
+    device.cb = mem::size_of::<DISPLAY_DEVICEW>() as u32;
+    // device 0 of an adapter is the monitor attached to it
+    let found = unsafe {
+        EnumDisplayDevicesW(
+            PCWSTR(device_name.as_ptr()),
+            0,
+            &mut device,
+            EDD_GET_DEVICE_INTERFACE_NAME,
+        )
+    };
+    if !found.as_bool() {
+        return None;
+    }
+    Some(unsafe { U16CString::from_ptr_str(device.DeviceID.as_ptr()).to_string_lossy() })
+}
+
+/// The EDID windows cached in the registry when the monitor was plugged in, `None` for virtual displays.
+fn get_monitor_edid(device_id: &str) -> Option<Edid> {
+    let (class, hardware_id, instance) = split_device_id(device_id)?;
+    let subkey = format!(
+        r"SYSTEM\CurrentControlSet\Enum\{}\{}\{}\Device Parameters",
+        class, hardware_id, instance
+    );
+    let subkey = U16CString::from_str(subkey).ok()?;
+
+    let read = |data: Option<&mut [u8]>, size: &mut u32| unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(subkey.as_ptr()),
+            w!("EDID"),
+            RRF_RT_REG_BINARY,
+            None,
+            data.map(|data| data.as_mut_ptr().cast()),
+            Some(size as *mut u32),
+        )
+    };
+    // EDIDs with extension blocks can be any multiple of 128 bytes, so ask for the size first
+    let mut size = 0;
+    if read(None, &mut size).is_err() {
+        return None;
+    }
+    let mut data = vec![0u8; size as usize];
+    if read(Some(&mut data), &mut size).is_err() {
+        return None;
+    }
+    data.truncate(size as usize);
+    parse_edid(&data).ok()
+}
+
+/// Split a device interface path like `\\?\DISPLAY#DEL4109#5&2f3e3a2&0&UID4352#{e6f07b5f-...}`
+/// into its class, hardware id and instance.
+///
+/// The instance names the adapter output the monitor is plugged into, so it survives reboots and renumbering.
+fn split_device_id(device_id: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = device_id.strip_prefix(r"\\?\")?.split('#');
+    Some((parts.next()?, parts.next()?, parts.next()?))
+}
+
+fn get_monitor_info_exw(h_monitor: HMONITOR) -> Result<MONITORINFOEXW> {
+    let mut monitor_info_exw: MONITORINFOEXW = unsafe { mem::zeroed() };
+    monitor_info_exw.monitorInfo.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+    let monitor_info_exw_ptr = <*mut _>::cast(&mut monitor_info_exw);
+
+    unsafe {
+        GetMonitorInfoW(h_monitor, monitor_info_exw_ptr).ok()?;
+    };
+    Ok(monitor_info_exw)
+}
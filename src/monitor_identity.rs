@@ -0,0 +1,321 @@
+use crate::prelude::monitor_id;
+use crate::prelude::Edid;
+use crate::prelude::MonitorId;
+use crate::prelude::MonitorInfo;
+
+/// What a [`MonitorIdentity`] was derived from, most stable first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IdentitySource {
+    /// The EDID serial, which follows the monitor to any port or machine
+    Serial,
+    /// The EDID model and the output it is plugged into, for monitors without a serial
+    ModelAndConnector,
+    /// Only the output, for monitors without an EDID
+    Connector,
+    /// Only the EDID model, which identical monitors share
+    Model,
+    /// The OS name like `\\.\DISPLAY1`, which windows renumbers
+    Name,
+}
+
+/// A key for a monitor that stays the same across reboots and re-plugging as far as the hardware allows
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorIdentity {
+    pub source: IdentitySource,
+    /// Like `DELA0A4/7MT0151T0CEL`, only comparable between identities from the same source
+    pub key: String,
+}
+
+impl MonitorIdentity {
+    /// The identity of a monitor, from the most stable attributes it has
+    pub fn of(info: &MonitorInfo) -> Self {
+        let model = info.edid.as_ref().map(edid_model);
+        let serial = info.edid.as_ref().and_then(edid_serial);
+        let (source, key) = match (model, serial, &info.connector) {
+            (Some(model), Some(serial), _) => {
+                (IdentitySource::Serial, format!("{}/{}", model, serial))
+            }
+            (Some(model), None, Some(connector)) => (
+                IdentitySource::ModelAndConnector,
+                format!("{}@{}", model, connector),
+            ),
+            (None, _, Some(connector)) => (IdentitySource::Connector, connector.clone()),
+            (Some(model), None, None) => (IdentitySource::Model, model),
+            (None, _, None) => (IdentitySource::Name, info.name.clone()),
+        };
+        MonitorIdentity { source, key }
+    }
+
+    /// A [`MonitorId`] of the identity, to use in place of [`MonitorInfo::id`] when saving per-monitor settings
+    pub fn id(&self) -> MonitorId {
+        monitor_id(&self.key)
+    }
+}
+
+/// Manufacturer and product code like `DELA0A4`, the hardware id windows uses
+fn edid_model(edid: &Edid) -> String {
+    format!("{}{:04X}", edid.manufacturer, edid.product_code)
+}
+
+/// The serial, unless it is a placeholder many monitors of a model share
+fn edid_serial(edid: &Edid) -> Option<String> {
+    let serial = edid.serial_string()?;
+    // 0x01010101 only means "no serial" in the numeric field, a serial string wins over it
+    let placeholder_number = edid.serial.is_none() && edid.serial_number == 0x01010101;
+    let placeholder = serial.chars().all(|c| c == '0') || placeholder_number;
+    (!placeholder).then_some(serial)
+}
+
+/// How sure we are that `saved` and `current` are the same physical monitor, from 0 to 1.
+///
+/// Matching serials are certain, other hardware attributes are weighed by how often unrelated monitors share them,
+/// and the OS name and position only break ties. Different models or serials are never the same monitor.
+pub fn match_confidence(saved: &MonitorInfo, current: &MonitorInfo) -> f64 {
+    let model = |info: &MonitorInfo| info.edid.as_ref().map(edid_model);
+    let serial = |info: &MonitorInfo| info.edid.as_ref().and_then(edid_serial);
+    let same = |a: Option<String>, b: Option<String>| match (a, b) {
+        (Some(a), Some(b)) => Some(a == b),
+        _ => None,
+    };
+    let same_model = same(model(saved), model(current));
+    let same_serial = same(serial(saved), serial(current));
+    let same_connector = same(saved.connector.clone(), current.connector.clone());
+    if same_model == Some(false) || same_serial == Some(false) {
+        return 0.0;
+    }
+
+    let hardware: f64 = match (same_serial, same_model, same_connector) {
+        (Some(true), _, _) => 1.0,
+        (_, Some(true), Some(true)) => 0.9,
+        (_, Some(true), _) => 0.6,
+        (_, None, Some(true)) => 0.5,
+        _ if saved.name == current.name => 0.3,
+        _ => 0.0,
+    };
+    let position = if saved.rect == current.rect {
+        0.2
+    } else if saved.rect.size() == current.rect.size() {
+        0.1
+    } else {
+        0.0
+    };
+    (hardware + position).min(1.0)
+}
+
+/// A monitor from a saved layout and the current monitor it most likely is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorMatch<'a> {
+    pub saved: &'a MonitorInfo,
+    /// `None` when no current monitor could be the saved one, or a likelier saved monitor took it
+    pub current: Option<&'a MonitorInfo>,
+    /// The [`match_confidence`] of the pair, 0 when unmatched
+    pub confidence: f64,
+}
+
+/// Reconcile a saved layout with the current monitors, like [`get_monitor_infos`](crate::prelude::get_monitor_infos) returns.
+///
+/// Each current monitor is matched to at most one saved monitor, most confident pairs first.
+/// Matches come back in the order of `saved`.
+pub fn match_layout<'a>(
+    saved: &'a [MonitorInfo],
+    current: &'a [MonitorInfo],
+) -> Vec<MonitorMatch<'a>> {
+    let mut pairs = Vec::new();
+    for (i, saved) in saved.iter().enumerate() {
+        for (j, current) in current.iter().enumerate() {
+            let confidence = match_confidence(saved, current);
+            if confidence > 0.0 {
+                pairs.push((confidence, i, j));
+            }
+        }
+    }
+    // stable, so ties go to the monitors enumerated first
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut matches: Vec<MonitorMatch> = saved
+        .iter()
+        .map(|saved| MonitorMatch {
+            saved,
+            current: None,
+            confidence: 0.0,
+        })
+        .collect();
+    let mut taken = vec![false; current.len()];
+    for (confidence, i, j) in pairs {
+        if matches[i].current.is_none() && !taken[j] {
+            taken[j] = true;
+            matches[i].current = Some(&current[j]);
+            matches[i].confidence = confidence;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::parse_edid;
+    use crate::prelude::Rect;
+    use crate::prelude::BASE_DPI;
+
+    const DELL_U2415: &[u8] = include_bytes!("../fixtures/edid/dell_u2415.bin");
+    const BOE_DISPLAYID2: &[u8] = include_bytes!("../fixtures/edid/boe_displayid2.bin");
+
+    fn info(name: &str, left: i32, connector: Option<&str>, edid: Option<Edid>) -> MonitorInfo {
+        let rect = Rect::new(left, 0, left + 1920, 1200);
+        MonitorInfo {
+            id: monitor_id(name),
+            name: name.to_string(),
+            rect,
+            work_area: rect,
+            is_primary: left == 0,
+            dpi: BASE_DPI,
+            connector: connector.map(str::to_string),
+            edid,
+        }
+    }
+
+    fn dell(serial: Option<&str>) -> Edid {
+        let mut edid = parse_edid(DELL_U2415).unwrap();
+        edid.serial = serial.map(str::to_string);
+        edid.serial_number = 0;
+        edid
+    }
+
+    #[test]
+    fn identity_falls_back_to_weaker_sources() {
+        let with_serial = info("a", 0, Some("DP-1"), Some(dell(Some("7MT0151T0CEL"))));
+        let identity = MonitorIdentity::of(&with_serial);
+        assert_eq!(identity.source, IdentitySource::Serial);
+        assert_eq!(identity.key, "DELA0A4/7MT0151T0CEL");
+
+        let no_serial = info("a", 0, Some("DP-1"), Some(dell(None)));
+        assert_eq!(
+            MonitorIdentity::of(&no_serial),
+            MonitorIdentity {
+                source: IdentitySource::ModelAndConnector,
+                key: "DELA0A4@DP-1".to_string(),
+            }
+        );
+        // all zero serials are placeholders
+        let placeholder = info("a", 0, None, Some(dell(Some("0000000000"))));
+        assert_eq!(
+            MonitorIdentity::of(&placeholder).source,
+            IdentitySource::Model
+        );
+        let mut placeholder_number = dell(None);
+        placeholder_number.serial_number = 0x01010101;
+        let placeholder = info("a", 0, None, Some(placeholder_number.clone()));
+        assert_eq!(
+            MonitorIdentity::of(&placeholder).source,
+            IdentitySource::Model
+        );
+        // a real serial string next to the placeholder number
+        placeholder_number.serial = Some("7MT0151T0CEL".to_string());
+        let with_serial = info("a", 0, None, Some(placeholder_number));
+        assert_eq!(
+            MonitorIdentity::of(&with_serial).key,
+            "DELA0A4/7MT0151T0CEL"
+        );
+        let no_edid = info("a", 0, Some("DP-1"), None);
+        assert_eq!(
+            MonitorIdentity::of(&no_edid).source,
+            IdentitySource::Connector
+        );
+        let nothing = info(r"\\.\DISPLAY1", 0, None, None);
+        assert_eq!(MonitorIdentity::of(&nothing).key, r"\\.\DISPLAY1");
+    }
+
+    #[test]
+    fn identity_survives_renumbering() {
+        let before = info(r"\\.\DISPLAY1", 0, Some("UID4352"), Some(dell(Some("A"))));
+        let after = info(r"\\.\DISPLAY3", 0, Some("UID4353"), Some(dell(Some("A"))));
+        assert_ne!(before.id, after.id);
+        assert_eq!(
+            MonitorIdentity::of(&before).id(),
+            MonitorIdentity::of(&after).id()
+        );
+    }
+
+    #[test]
+    fn matches_swapped_monitors_by_serial() {
+        let saved = vec![
+            info(r"\\.\DISPLAY1", 0, Some("UID1"), Some(dell(Some("A")))),
+            info(r"\\.\DISPLAY2", 1920, Some("UID2"), Some(dell(Some("B")))),
+        ];
+        // windows renumbered them and the user swapped the cables
+        let current = vec![
+            info(r"\\.\DISPLAY1", 0, Some("UID1"), Some(dell(Some("B")))),
+            info(r"\\.\DISPLAY2", 1920, Some("UID2"), Some(dell(Some("A")))),
+        ];
+        let matches = match_layout(&saved, &current);
+        assert_eq!(matches[0].current, Some(&current[1]));
+        assert_eq!(matches[1].current, Some(&current[0]));
+        assert!(matches.iter().all(|m| m.confidence >= 1.0));
+    }
+
+    #[test]
+    fn identical_monitors_without_serials_match_by_connector() {
+        let saved = vec![
+            info("left", 0, Some("DP-1"), Some(dell(None))),
+            info("right", 1920, Some("DP-2"), Some(dell(None))),
+        ];
+        // arranged the other way around in display settings
+        let current = vec![
+            info("right", 0, Some("DP-2"), Some(dell(None))),
+            info("left", 1920, Some("DP-1"), Some(dell(None))),
+        ];
+        let matches = match_layout(&saved, &current);
+        assert_eq!(
+            matches[0].current.unwrap().connector.as_deref(),
+            Some("DP-1")
+        );
+        assert_eq!(
+            matches[1].current.unwrap().connector.as_deref(),
+            Some("DP-2")
+        );
+        // same size on a different position
+        assert_eq!(matches[0].confidence, 1.0);
+    }
+
+    #[test]
+    fn replaced_monitor_is_unmatched() {
+        let saved = vec![
+            info(
+                "primary",
+                0,
+                Some("eDP-1"),
+                Some(parse_edid(BOE_DISPLAYID2).unwrap()),
+            ),
+            info("external", 1920, Some("DP-1"), Some(dell(Some("A")))),
+        ];
+        let current = vec![
+            info(
+                "primary",
+                0,
+                Some("eDP-1"),
+                Some(parse_edid(BOE_DISPLAYID2).unwrap()),
+            ),
+            info("external", 1920, Some("DP-1"), Some(dell(Some("B")))),
+        ];
+        let matches = match_layout(&saved, &current);
+        assert_eq!(matches[0].current, Some(&current[0]));
+        assert_eq!(matches[1].current, None);
+        assert_eq!(matches[1].confidence, 0.0);
+        assert_eq!(match_confidence(&saved[1], &current[1]), 0.0);
+    }
+
+    #[test]
+    fn virtual_monitors_fall_back_to_name_and_position() {
+        let saved = vec![info("screen", 0, None, None)];
+        let moved = vec![info("screen", 1920, None, None)];
+        let renamed = vec![info("other", 0, None, None)];
+        assert_eq!(match_layout(&saved, &saved)[0].confidence, 0.5);
+        // same size, elsewhere
+        assert_eq!(match_layout(&saved, &moved)[0].confidence, 0.4);
+        assert_eq!(match_layout(&saved, &renamed)[0].confidence, 0.2);
+        assert_eq!(match_layout(&saved, &[])[0].current, None);
+    }
+}
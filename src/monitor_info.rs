@@ -1,90 +1,107 @@
-#[cfg(target_arch = "x86")]
-use std::arch::x86::_mm_shuffle_epi8;
-use std::mem;
-use widestring::U16CString;
-use windows::core::Result;
-use windows::Win32::Foundation::BOOL;
-use windows::Win32::Foundation::LPARAM;
-use windows::Win32::Foundation::RECT;
-use windows::Win32::Graphics::Gdi::EnumDisplayMonitors;
-use windows::Win32::Graphics::Gdi::GetMonitorInfoW;
-use windows::Win32::Graphics::Gdi::HDC;
-use windows::Win32::Graphics::Gdi::HMONITOR;
-use windows::Win32::Graphics::Gdi::MONITORINFOEXW;
+use crate::prelude::Edid;
+use crate::prelude::HasTopLeft;
+use crate::prelude::Point;
+use crate::prelude::Rect;
+use crate::prelude::Size;
 
 pub type MonitorId = u32;
 
-#[derive(Debug)]
+/// The DPI of a monitor at 100% scale
+pub const BASE_DPI: u32 = 96;
+
+/// Derive a monitor's id from its name, using the same algorithm as the screen crate
+#[cfg(feature = "id")]
+pub fn monitor_id(name: &str) -> MonitorId {
+    fxhash::hash32(name.as_bytes())
+}
+
+/// Derive a monitor's id from its name using 32 bit FNV-1a, since fxhash is disabled
+#[cfg(not(feature = "id"))]
+pub fn monitor_id(name: &str) -> MonitorId {
+    name.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MonitorInfo {
     pub id: MonitorId,
     pub name: String,
-    pub rect: RECT,
-    pub work_area: RECT, // the area of the monitor not covered by the taskbar
+    pub rect: Rect,
+    pub work_area: Rect, // the area of the monitor not covered by the taskbar
     pub is_primary: bool,
+    /// Effective DPI, [`BASE_DPI`] at 100% scale and 144 at 150%
+    pub dpi: u32,
+    /// The output the monitor is plugged into, like `DP-1` on X11 or the display target instance on windows
+    pub connector: Option<String>,
+    /// What the monitor reports about itself, `None` for virtual displays and when the OS does not expose it
+    pub edid: Option<Edid>,
 }
 
-pub fn get_monitor_infos() -> Result<Vec<MonitorInfo>> {
-    // box it up so we can pass it to the callback
-    let results: *mut Vec<MONITORINFOEXW> = Box::into_raw(Box::default());
-
-    // use proc method to iterate monitors and collect into results vec
-    unsafe {
-        EnumDisplayMonitors(
-            HDC::default(),
-            None,
-            Some(monitor_enum_proc),
-            LPARAM(results as isize),
-        )
-        .ok()?;
-    };
-
-    // convert results back into a vec
-    let results = unsafe { &Box::from_raw(results) };
-
-    // convert vec of MONITORINFOEXW into vec of MonitorInfo
-    let results = results
-        .iter()
-        .map(|info| {
-            let sz_device_ptr = info.szDevice.as_ptr();
-            let sz_device_string =
-                unsafe { U16CString::from_ptr_str(sz_device_ptr).to_string_lossy() };
-            MonitorInfo {
-                id: fxhash::hash32(sz_device_string.as_bytes()), // same algorithm as screen crate
-                name: sz_device_string,
-                rect: info.monitorInfo.rcMonitor,
-                work_area: info.monitorInfo.rcWork,
-                is_primary: info.monitorInfo.dwFlags == 1,
-            }
-        })
-        .collect::<Vec<MonitorInfo>>();
-    Ok(results)
-}
+impl MonitorInfo {
+    /// Convert a point in virtual desktop coordinates to one relative to the monitor's top left
+    pub fn to_local(&self, point: impl Into<Point>) -> Point {
+        point.into() - self.rect.top_left()
+    }
 
-extern "system" fn monitor_enum_proc(
-    h_monitor: HMONITOR,
-    _: HDC,
-    _: *mut RECT,
-    data: LPARAM,
-) -> BOOL {
-    let results = unsafe { Box::from_raw(data.0 as *mut Vec<MONITORINFOEXW>) };
-    let results = Box::leak(results);
-
-    match get_monitor_info_exw(h_monitor) {
-        Ok(monitor_info_exw) => {
-            results.push(monitor_info_exw);
-            BOOL::from(true)
-        }
-        Err(_) => BOOL::from(false),
+    /// Convert a point relative to the monitor's top left to virtual desktop coordinates
+    pub fn to_global(&self, point: impl Into<Point>) -> Point {
+        point.into() + self.rect.top_left()
+    }
+
+    pub fn rect_to_local(&self, rect: Rect) -> Rect {
+        rect - self.rect.top_left()
+    }
+
+    pub fn rect_to_global(&self, rect: Rect) -> Rect {
+        rect + self.rect.top_left()
+    }
+
+    /// The manufacturer from the EDID, like `Dell`
+    pub fn make(&self) -> Option<&str> {
+        self.edid.as_ref().map(Edid::vendor)
     }
-}
 
-fn get_monitor_info_exw(h_monitor: HMONITOR) -> Result<MONITORINFOEXW> {
-    let mut monitor_info_exw: MONITORINFOEXW = unsafe { mem::zeroed() };
-    monitor_info_exw.monitorInfo.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
-    let monitor_info_exw_ptr = <*mut _>::cast(&mut monitor_info_exw);
+    /// The model from the EDID, like `DELL U2415`
+    pub fn model(&self) -> Option<String> {
+        self.edid.as_ref().map(Edid::model)
+    }
+
+    pub fn serial(&self) -> Option<String> {
+        self.edid.as_ref().and_then(Edid::serial_string)
+    }
+
+    /// How many physical pixels make up a logical pixel, 1.5 at 150%
+    pub fn scale_factor(&self) -> f64 {
+        self.dpi as f64 / BASE_DPI as f64
+    }
+
+    /// The size of the monitor in logical pixels
+    pub fn logical_size(&self) -> Size {
+        self.rect.size().scale(1.0 / self.scale_factor())
+    }
 
-    unsafe {
-        GetMonitorInfoW(h_monitor, monitor_info_exw_ptr).ok()?;
-    };
-    Ok(monitor_info_exw)
+    /// Convert a rect in logical pixels, the DPI scaled ones windows are laid out in, to the physical pixels backends capture.
+    ///
+    /// Both spaces share the monitor's top left, edges are rounded as in [`Rect::scale`].
+    pub fn logical_to_physical(&self, rect: Rect) -> Rect {
+        let origin = self.rect.top_left();
+        (rect - origin).scale(self.scale_factor()) + origin
+    }
+
+    pub fn physical_to_logical(&self, rect: Rect) -> Rect {
+        let origin = self.rect.top_left();
+        (rect - origin).scale(1.0 / self.scale_factor()) + origin
+    }
+
+    pub fn logical_point_to_physical(&self, point: impl Into<Point>) -> Point {
+        let origin = self.rect.top_left();
+        (point.into() - origin).scale(self.scale_factor()) + origin
+    }
+
+    pub fn physical_point_to_logical(&self, point: impl Into<Point>) -> Point {
+        let origin = self.rect.top_left();
+        (point.into() - origin).scale(1.0 / self.scale_factor()) + origin
+    }
 }
@@ -1,11 +1,5 @@
-use image::RgbaImage;
-#[cfg(target_arch = "x86")]
-use std::arch::x86::_mm_shuffle_epi8;
 use std::mem;
 use std::rc::Rc;
-use windows::core::Result;
-use windows::Win32::Foundation::RECT;
-use windows::Win32::Foundation::S_FALSE;
 use windows::Win32::Graphics::Gdi::CreateCompatibleBitmap;
 use windows::Win32::Graphics::Gdi::CreateCompatibleDC;
 use windows::Win32::Graphics::Gdi::DeleteDC;
@@ -25,23 +19,39 @@ use windows::Win32::Graphics::Gdi::RGBQUAD;
 use windows::Win32::Graphics::Gdi::SRCCOPY;
 use windows::Win32::Graphics::Gdi::STRETCH_HALFTONE;
 
-use crate::prelude::bgra_to_rgba;
+use crate::prelude::capture_virtual_screen_from;
+use crate::prelude::check_buffer_size;
 use crate::prelude::get_all_monitors;
+use crate::prelude::get_stitched_capturer;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::CaptureSource;
+use crate::prelude::Frame;
+use crate::prelude::FrameBuffer;
+use crate::prelude::FrameInfo;
 use crate::prelude::HasHeight;
-use crate::prelude::HasLeft;
-use crate::prelude::HasTop;
+use crate::prelude::HasTopLeft;
 use crate::prelude::HasWidth;
+use crate::prelude::Intersect;
 use crate::prelude::Metrics;
 use crate::prelude::Monitor;
+use crate::prelude::MonitorInfo;
+use crate::prelude::PixelFormat;
+use crate::prelude::Rect;
+use crate::prelude::StitchedCapturer;
+use crate::prelude::VirtualScreenOptions;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+use crate::prelude::NATIVE_PIXEL_FORMAT;
 
 pub struct MonitorRegionCapturer {
     pub monitor: Rc<Monitor>,
-    pub capture_region: RECT,
+    pub capture_region: Rect,
+    pub alpha_policy: AlphaPolicy,
     device_context: HDC,
     bitmap: HBITMAP,
 }
 
-pub fn get_full_monitor_capturers() -> Result<Vec<MonitorRegionCapturer>> {
+pub fn get_full_monitor_capturers() -> WincResult<Vec<MonitorRegionCapturer>> {
     let monitors = get_all_monitors()?;
     let mut capturers = Vec::new();
 
@@ -54,7 +64,15 @@ pub fn get_full_monitor_capturers() -> Result<Vec<MonitorRegionCapturer>> {
     Ok(capturers)
 }
 
-pub fn get_monitor_capturer(monitor: Rc<Monitor>, capture_region: RECT) -> MonitorRegionCapturer {
+/// Same as [`get_full_monitor_capturers`], but type-erased
+pub fn get_full_monitor_capture_sources() -> WincResult<Vec<Box<dyn CaptureSource>>> {
+    Ok(get_full_monitor_capturers()?
+        .into_iter()
+        .map(|capturer| Box::new(capturer) as Box<dyn CaptureSource>)
+        .collect())
+}
+
+pub fn get_monitor_capturer(monitor: Rc<Monitor>, capture_region: Rect) -> MonitorRegionCapturer {
     let capture_device_context = unsafe { CreateCompatibleDC(monitor.device_context) };
     let bitmap = unsafe {
         CreateCompatibleBitmap(
@@ -74,9 +92,55 @@ pub fn get_monitor_capturer(monitor: Rc<Monitor>, capture_region: RECT) -> Monit
         device_context: capture_device_context,
         bitmap,
         capture_region,
+        alpha_policy: AlphaPolicy::default(),
     }
 }
 
+/// Capture a region spanning several monitors, each monitor capturing the piece it shows.
+///
+/// Parts of the region no monitor shows are filled, see [`StitchedCapturer::with_fill`].
+/// Frames belong to the monitor showing most of the region, the primary monitor when several show as much.
+pub fn get_multi_monitor_capturer(
+    monitors: &[Rc<Monitor>],
+    capture_region: Rect,
+) -> WincResult<StitchedCapturer<MonitorRegionCapturer>> {
+    let pieces = monitors
+        .iter()
+        .filter_map(|monitor| {
+            let piece = monitor.info.rect.intersection(&capture_region)?;
+            Some(get_monitor_capturer(monitor.clone(), piece))
+        })
+        .collect();
+    get_stitched_capturer(pieces, capture_region)
+}
+
+/// Capture every monitor into one frame laid out like the display settings, see [`VirtualScreenOptions`]
+pub fn capture_virtual_screen(
+    options: &VirtualScreenOptions,
+    metrics: &mut Metrics,
+) -> WincResult<Frame> {
+    let capturers = if options.work_areas_only {
+        get_all_monitors()?
+            .into_iter()
+            .map(|monitor| {
+                let region = options.monitor_region(&monitor.info);
+                get_monitor_capturer(Rc::new(monitor), region)
+            })
+            .collect()
+    } else {
+        get_full_monitor_capturers()?
+    };
+    capture_virtual_screen_from(capturers, options, metrics)
+}
+
+/// Same as [`get_monitor_capturer`], but type-erased
+pub fn get_monitor_capture_source(
+    monitor: Rc<Monitor>,
+    capture_region: Rect,
+) -> Box<dyn CaptureSource> {
+    Box::new(get_monitor_capturer(monitor, capture_region))
+}
+
 impl Drop for MonitorRegionCapturer {
     fn drop(&mut self) {
         unsafe {
@@ -90,10 +154,42 @@ impl Drop for MonitorRegionCapturer {
     }
 }
 impl MonitorRegionCapturer {
-    pub fn capture(&self, metrics: &mut Metrics) -> Result<RgbaImage> {
+    /// Change what happens to alpha, [`AlphaPolicy::ForceOpaque`] by default
+    pub fn with_alpha_policy(mut self, alpha_policy: AlphaPolicy) -> Self {
+        self.alpha_policy = alpha_policy;
+        self
+    }
+
+    #[cfg(feature = "image")]
+    pub fn capture(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        CaptureSource::capture(self, metrics)
+    }
+
+    pub fn capture_raw(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        self.capture_as(NATIVE_PIXEL_FORMAT, metrics)
+    }
+
+    pub fn capture_as(&self, format: PixelFormat, metrics: &mut Metrics) -> WincResult<Frame> {
+        CaptureSource::capture_as(self, format, metrics)
+    }
+
+    pub fn capture_into(
+        &self,
+        frame: &mut FrameBuffer,
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        CaptureSource::capture_into(self, frame, metrics)
+    }
+
+    pub fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
         let capture_region_width = self.capture_region.width();
         let capture_region_height = self.capture_region.height();
+        check_buffer_size(
+            (capture_region_width * capture_region_height) as usize * 4,
+            buffer.len(),
+        )?;
         // todo: try https://learn.microsoft.com/en-us/windows/win32/api/dxgi1_2/nf-dxgi1_2-idxgioutputduplication-acquirenextframe
+        let source = self.monitor.info.to_local(self.capture_region.top_left());
         unsafe {
             metrics.begin("blit");
             StretchBlt(
@@ -103,8 +199,8 @@ impl MonitorRegionCapturer {
                 capture_region_width,
                 capture_region_height,
                 self.monitor.device_context,
-                self.capture_region.left()-self.monitor.info.rect.left(),
-                self.capture_region.top()-self.monitor.info.rect.top(),
+                source.x,
+                source.y,
                 capture_region_width,
                 capture_region_height,
                 SRCCOPY,
@@ -130,9 +226,7 @@ impl MonitorRegionCapturer {
             bmiColors: [RGBQUAD::default(); 1],
         };
 
-        let mut data =
-            vec![0u8; (capture_region_width * capture_region_height) as usize * 4];
-        let buf_prt = data.as_ptr() as *mut _;
+        let buf_prt = buffer.as_mut_ptr() as *mut _;
 
         metrics.begin("getdibits");
         let err = unsafe {
@@ -149,7 +243,7 @@ impl MonitorRegionCapturer {
         metrics.end("getdibits");
 
         if err {
-            return Err(windows::core::Error::new(S_FALSE, "No RGBA data returned"));
+            return Err(WincError::EmptyCapture);
         }
 
         let mut bitmap = BITMAP::default();
@@ -166,17 +260,24 @@ impl MonitorRegionCapturer {
         }
         metrics.end("getobject");
 
-        metrics.begin("shuffle");
-        bgra_to_rgba(data.as_mut_slice());
-        metrics.end("shuffle");
-
-        metrics.begin("image");
-        let data = RgbaImage::from_vec(
-            capture_region_width as u32,
-            capture_region_height as u32,
-            data,
-        );
-        metrics.end("image");
-        data.ok_or_else(|| windows::core::Error::new(S_FALSE, "Invalid image data"))
+        Ok(())
+    }
+}
+
+impl CaptureSource for MonitorRegionCapturer {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        MonitorRegionCapturer::capture_bgra_into(self, buffer, metrics)
+    }
+
+    fn region(&self) -> Rect {
+        self.capture_region
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        &self.monitor.info
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        self.alpha_policy
     }
 }
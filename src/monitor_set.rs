@@ -0,0 +1,244 @@
+use std::rc::Rc;
+
+use crate::prelude::ContainsPoint;
+use crate::prelude::HasArea;
+use crate::prelude::MonitorId;
+use crate::prelude::MonitorInfo;
+use crate::prelude::Point;
+
+/// A monitor from any backend, so [`MonitorSet`] can query them all the same way
+pub trait HasMonitorInfo {
+    fn info(&self) -> &MonitorInfo;
+}
+
+impl HasMonitorInfo for MonitorInfo {
+    fn info(&self) -> &MonitorInfo {
+        self
+    }
+}
+
+impl<T: HasMonitorInfo + ?Sized> HasMonitorInfo for Rc<T> {
+    fn info(&self) -> &MonitorInfo {
+        (**self).info()
+    }
+}
+
+/// The monitors attached to the machine in enumeration order, with lookups so callers need not filter by hand
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorSet<M> {
+    monitors: Vec<M>,
+}
+
+impl<M> Default for MonitorSet<M> {
+    fn default() -> Self {
+        MonitorSet {
+            monitors: Vec::new(),
+        }
+    }
+}
+
+impl<M: HasMonitorInfo> MonitorSet<M> {
+    pub fn new(monitors: Vec<M>) -> Self {
+        MonitorSet { monitors }
+    }
+
+    pub fn len(&self) -> usize {
+        self.monitors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.monitors.is_empty()
+    }
+
+    /// Monitors in the order the OS enumerated them
+    pub fn iter(&self) -> std::slice::Iter<'_, M> {
+        self.monitors.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<M> {
+        self.monitors
+    }
+
+    /// The monitor at `index` in enumeration order
+    pub fn by_index(&self, index: usize) -> Option<&M> {
+        self.monitors.get(index)
+    }
+
+    /// The monitor holding the taskbar, whose top left is the desktop origin
+    pub fn primary(&self) -> Option<&M> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.info().is_primary)
+    }
+
+    /// The monitor showing `point`, the first one enumerated when monitors are mirrored
+    pub fn at_point(&self, point: impl Into<Point>) -> Option<&M> {
+        let point = point.into();
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.info().rect.contains_point(point))
+    }
+
+    pub fn by_id(&self, id: MonitorId) -> Option<&M> {
+        self.monitors.iter().find(|monitor| monitor.info().id == id)
+    }
+
+    /// The monitor with an OS name like `\\.\DISPLAY1` on windows or `DP-1` on X11
+    pub fn by_name(&self, name: &str) -> Option<&M> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.info().name == name)
+    }
+
+    /// The monitor with the most pixels, the first one enumerated on a tie
+    pub fn largest(&self) -> Option<&M> {
+        self.monitors.iter().reduce(|largest, monitor| {
+            if monitor.info().rect.area() > largest.info().rect.area() {
+                monitor
+            } else {
+                largest
+            }
+        })
+    }
+
+    /// Monitors from left to right, and top to bottom among monitors with the same left edge
+    pub fn ordered(&self) -> impl Iterator<Item = &M> {
+        let mut ordered: Vec<&M> = self.monitors.iter().collect();
+        ordered.sort_by_key(|monitor| {
+            let rect = monitor.info().rect;
+            (rect.left, rect.top)
+        });
+        ordered.into_iter()
+    }
+
+    /// The monitor showing `point`, or the closest one when the point is in a gap between monitors or off every screen
+    pub fn nearest(&self, point: impl Into<Point>) -> Option<&M> {
+        let point = point.into();
+        // distances near the ends of i32 overflow it, and their squares overflow i64
+        let (x, y) = (i128::from(point.x), i128::from(point.y));
+        self.monitors.iter().min_by_key(|monitor| {
+            let rect = monitor.info().rect;
+            let (left, top) = (i128::from(rect.left), i128::from(rect.top));
+            // right and bottom are exclusive, so the last pixel inside is one before them
+            let (right, bottom) = (i128::from(rect.right) - 1, i128::from(rect.bottom) - 1);
+            let dx = (left - x).max(x - right).max(0);
+            let dy = (top - y).max(y - bottom).max(0);
+            dx * dx + dy * dy
+        })
+    }
+}
+
+impl<M: HasMonitorInfo> FromIterator<M> for MonitorSet<M> {
+    fn from_iter<I: IntoIterator<Item = M>>(iter: I) -> Self {
+        MonitorSet::new(iter.into_iter().collect())
+    }
+}
+
+impl<M> IntoIterator for MonitorSet<M> {
+    type Item = M;
+    type IntoIter = std::vec::IntoIter<M>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.monitors.into_iter()
+    }
+}
+
+impl<'a, M> IntoIterator for &'a MonitorSet<M> {
+    type Item = &'a M;
+    type IntoIter = std::slice::Iter<'a, M>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.monitors.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::prelude::monitor_id;
+    use crate::prelude::Rect;
+    use crate::prelude::SyntheticLayout;
+    use crate::prelude::SyntheticMonitor;
+    use crate::prelude::SyntheticPattern;
+
+    const PATTERN: SyntheticPattern = SyntheticPattern::Gradient;
+
+    /// A 1440p primary, a portrait monitor on its left and a small one above and to the right,
+    /// enumerated in the order windows tends to report them
+    fn monitors() -> MonitorSet<Rc<SyntheticMonitor>> {
+        SyntheticLayout::new()
+            .with_monitor("top", Rect::new(2560, -720, 3840, 0), false, PATTERN)
+            .with_monitor("primary", Rect::new(0, 0, 2560, 1440), true, PATTERN)
+            .with_monitor("left", Rect::new(-1080, -400, 0, 1520), false, PATTERN)
+            .monitor_set()
+    }
+
+    fn name(monitor: Option<&Rc<SyntheticMonitor>>) -> Option<&str> {
+        monitor.map(|monitor| monitor.info.name.as_str())
+    }
+
+    fn names<'a>(monitors: impl Iterator<Item = &'a Rc<SyntheticMonitor>>) -> Vec<&'a str> {
+        monitors.map(|monitor| monitor.info.name.as_str()).collect()
+    }
+
+    #[test]
+    fn lookups() {
+        let monitors = monitors();
+        assert_eq!(name(monitors.primary()), Some("primary"));
+        assert_eq!(name(monitors.by_name("left")), Some("left"));
+        assert_eq!(name(monitors.by_id(monitor_id("top"))), Some("top"));
+        assert_eq!(name(monitors.by_index(0)), Some("top"));
+        assert_eq!(name(monitors.largest()), Some("primary"));
+        assert_eq!(name(monitors.at_point((-1, -1))), Some("left"));
+        assert_eq!(name(monitors.at_point((2560, -1))), Some("top"));
+        assert_eq!(name(monitors.at_point((2560, 0))), None);
+        assert_eq!(name(monitors.by_name("missing")), None);
+        assert_eq!(name(monitors.by_index(3)), None);
+
+        let empty = SyntheticLayout::new().monitor_set();
+        assert!(empty.is_empty());
+        assert_eq!(name(empty.primary()), None);
+        assert_eq!(name(empty.largest()), None);
+        assert_eq!(name(empty.nearest((0, 0))), None);
+    }
+
+    #[test]
+    fn ordered_left_to_right() {
+        let monitors = monitors();
+        assert_eq!(names(monitors.ordered()), ["left", "primary", "top"]);
+        // iteration keeps enumeration order
+        assert_eq!(names(monitors.iter()), ["top", "primary", "left"]);
+
+        let stacked = SyntheticLayout::new()
+            .with_monitor("bottom", Rect::new(0, 1080, 1920, 2160), false, PATTERN)
+            .with_monitor("top", Rect::new(0, 0, 1920, 1080), true, PATTERN)
+            .monitor_set();
+        assert_eq!(names(stacked.ordered()), ["top", "bottom"]);
+    }
+
+    #[test]
+    fn nearest_monitor_for_points_off_screen() {
+        let monitors = monitors();
+        // on a monitor
+        assert_eq!(name(monitors.nearest((100, 100))), Some("primary"));
+        // in the gap right of the primary and below the top monitor
+        assert_eq!(name(monitors.nearest((3000, 500))), Some("primary"));
+        assert_eq!(name(monitors.nearest((3000, 10))), Some("top"));
+        // far above everything
+        assert_eq!(name(monitors.nearest((3000, -5000))), Some("top"));
+        assert_eq!(name(monitors.nearest((-5000, 0))), Some("left"));
+        // just past the exclusive bottom edge of the primary
+        assert_eq!(name(monitors.nearest((1000, 1440))), Some("primary"));
+        // the ends of the coordinate space
+        assert_eq!(name(monitors.nearest((i32::MIN, i32::MIN))), Some("left"));
+        assert_eq!(name(monitors.nearest((i32::MAX, i32::MIN))), Some("top"));
+        assert_eq!(
+            name(monitors.nearest((i32::MAX, i32::MAX))),
+            Some("primary")
+        );
+        assert_eq!(name(monitors.nearest((i32::MIN, i32::MAX))), Some("left"));
+    }
+}
@@ -0,0 +1,261 @@
+#[cfg(feature = "image")]
+use image::DynamicImage;
+#[cfg(feature = "image")]
+use image::GrayImage;
+#[cfg(feature = "image")]
+use image::RgbImage;
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+#[cfg(feature = "image")]
+use crate::prelude::bgra_to_rgba;
+#[cfg(feature = "bgra_to_rgba")]
+use crate::prelude::convert_bgra_in_place;
+#[cfg(feature = "bgra_to_rgba")]
+use crate::prelude::convert_bgra_in_place_opaque;
+use crate::prelude::Metrics;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+
+/// The layout of the bytes of each pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PixelFormat {
+    /// Blue, green, red, alpha. What GDI and X11 hand back.
+    Bgra8,
+    /// Red, green, blue, alpha. What `image::RgbaImage` expects.
+    Rgba8,
+    /// Red, green, blue, alpha dropped
+    Rgb8,
+    /// Blue, green, red, alpha dropped. What OpenCV expects.
+    Bgr8,
+    /// BT.601 luma
+    Gray8,
+}
+
+impl PixelFormat {
+    pub const ALL: [PixelFormat; 5] = [
+        PixelFormat::Bgra8,
+        PixelFormat::Rgba8,
+        PixelFormat::Rgb8,
+        PixelFormat::Bgr8,
+        PixelFormat::Gray8,
+    ];
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Bgra8 | PixelFormat::Rgba8 => 4,
+            PixelFormat::Rgb8 | PixelFormat::Bgr8 => 3,
+            PixelFormat::Gray8 => 1,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, PixelFormat::Bgra8 | PixelFormat::Rgba8)
+    }
+}
+
+/// What to do with the alpha channel backends hand back.
+///
+/// GDI often reports alpha 0 for pixels that are on screen and so opaque, X11 leaves it undefined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlphaPolicy {
+    /// Set alpha to 255, in the same pass that reorders the channels
+    #[default]
+    ForceOpaque,
+    /// Keep whatever alpha the backend wrote
+    Preserve,
+    /// Capture RGBA as RGB and BGRA as BGR
+    Drop,
+}
+
+impl AlphaPolicy {
+    /// The format a capture requested as `format` ends up in
+    pub fn output_format(&self, format: PixelFormat) -> PixelFormat {
+        match (self, format) {
+            (AlphaPolicy::Drop, PixelFormat::Bgra8) => PixelFormat::Bgr8,
+            (AlphaPolicy::Drop, PixelFormat::Rgba8) => PixelFormat::Rgb8,
+            _ => format,
+        }
+    }
+}
+
+/// The format backends produce, RGBA when the `bgra_to_rgba` feature is enabled and BGRA otherwise
+#[cfg(feature = "bgra_to_rgba")]
+pub const NATIVE_PIXEL_FORMAT: PixelFormat = PixelFormat::Rgba8;
+#[cfg(not(feature = "bgra_to_rgba"))]
+pub const NATIVE_PIXEL_FORMAT: PixelFormat = PixelFormat::Bgra8;
+
+/// Fail with [`WincError::SizeMismatch`] unless a buffer has the expected length
+pub(crate) fn check_buffer_size(expected: usize, actual: usize) -> WincResult<()> {
+    if actual != expected {
+        return Err(WincError::SizeMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Convert a BGRA buffer to `alpha.output_format(format)` in place, returning how many bytes now hold the result
+pub(crate) fn convert_from_bgra(
+    data: &mut [u8],
+    format: PixelFormat,
+    alpha: AlphaPolicy,
+    #[allow(unused_variables)] metrics: &mut Metrics,
+) -> WincResult<usize> {
+    let format = alpha.output_format(format);
+    let opaque = alpha == AlphaPolicy::ForceOpaque;
+    if format == PixelFormat::Bgra8 && !opaque {
+        return Ok(data.len());
+    }
+    #[cfg(feature = "bgra_to_rgba")]
+    {
+        metrics.begin("shuffle");
+        let len = if opaque {
+            convert_bgra_in_place_opaque(data, format)
+        } else {
+            convert_bgra_in_place(data, format)
+        };
+        metrics.end("shuffle");
+        Ok(len)
+    }
+    #[cfg(not(feature = "bgra_to_rgba"))]
+    if format == PixelFormat::Bgra8 {
+        for pixel in data.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+        Ok(data.len())
+    } else {
+        Err(WincError::Unsupported(format!(
+            "converting to {:?} needs the bgra_to_rgba feature",
+            format
+        )))
+    }
+}
+
+/// Tightly packed pixels, without depending on the `image` crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub data: Vec<u8>,
+}
+
+impl RawImage {
+    pub fn new(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> WincResult<Self> {
+        check_buffer_size(
+            width as usize * height as usize * format.bytes_per_pixel(),
+            data.len(),
+        )?;
+        Ok(RawImage {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    /// Wrap a BGRA buffer straight from a backend, converting it to [`NATIVE_PIXEL_FORMAT`]
+    pub fn from_bgra(
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        metrics: &mut Metrics,
+    ) -> WincResult<Self> {
+        RawImage::from_bgra_as(width, height, data, NATIVE_PIXEL_FORMAT, metrics)
+    }
+
+    /// Wrap a BGRA buffer straight from a backend, converting it to `format` in place.
+    ///
+    /// Without the `bgra_to_rgba` feature only [`PixelFormat::Bgra8`] is available.
+    pub fn from_bgra_as(
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        format: PixelFormat,
+        metrics: &mut Metrics,
+    ) -> WincResult<Self> {
+        RawImage::from_bgra_with_alpha(width, height, data, format, AlphaPolicy::Preserve, metrics)
+    }
+
+    /// [`from_bgra_as`](Self::from_bgra_as), applying `alpha` in the same pass.
+    ///
+    /// With [`AlphaPolicy::Drop`] the image is in [`AlphaPolicy::output_format`] rather than `format`.
+    pub fn from_bgra_with_alpha(
+        width: u32,
+        height: u32,
+        mut data: Vec<u8>,
+        format: PixelFormat,
+        alpha: AlphaPolicy,
+        metrics: &mut Metrics,
+    ) -> WincResult<Self> {
+        check_buffer_size(width as usize * height as usize * 4, data.len())?;
+        let len = convert_from_bgra(data.as_mut_slice(), format, alpha, metrics)?;
+        data.truncate(len);
+        RawImage::new(width, height, alpha.output_format(format), data)
+    }
+
+    /// The pixel at `(x, y)` as RGBA, whatever the format of the image
+    pub fn rgba_at(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * self.format.bytes_per_pixel();
+        let pixel = &self.data[i..i + self.format.bytes_per_pixel()];
+        match self.format {
+            PixelFormat::Bgra8 => [pixel[2], pixel[1], pixel[0], pixel[3]],
+            PixelFormat::Rgba8 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            PixelFormat::Rgb8 => [pixel[0], pixel[1], pixel[2], 255],
+            PixelFormat::Bgr8 => [pixel[2], pixel[1], pixel[0], 255],
+            PixelFormat::Gray8 => [pixel[0], pixel[0], pixel[0], 255],
+        }
+    }
+
+    #[cfg(feature = "image")]
+    pub fn into_rgba_image(self) -> WincResult<RgbaImage> {
+        let data = match self.format {
+            PixelFormat::Rgba8 => self.data,
+            PixelFormat::Bgra8 => {
+                let mut data = self.data;
+                bgra_to_rgba(data.as_mut_slice());
+                data
+            }
+            _ => {
+                let mut data = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        data.extend_from_slice(&self.rgba_at(x, y));
+                    }
+                }
+                data
+            }
+        };
+        let actual = data.len();
+        RgbaImage::from_vec(self.width, self.height, data).ok_or(WincError::SizeMismatch {
+            expected: self.width as usize * self.height as usize * 4,
+            actual,
+        })
+    }
+
+    /// Wrap the pixels in the matching `image` type, swapping channels in place when needed
+    #[cfg(feature = "image")]
+    pub fn into_dynamic_image(self) -> WincResult<DynamicImage> {
+        let (width, height) = (self.width, self.height);
+        let expected = width as usize * height as usize * self.format.bytes_per_pixel();
+        let actual = self.data.len();
+        let mut data = self.data;
+        let image = match self.format {
+            PixelFormat::Rgba8 => RgbaImage::from_vec(width, height, data).map(DynamicImage::from),
+            PixelFormat::Bgra8 => {
+                bgra_to_rgba(data.as_mut_slice());
+                RgbaImage::from_vec(width, height, data).map(DynamicImage::from)
+            }
+            PixelFormat::Rgb8 => RgbImage::from_vec(width, height, data).map(DynamicImage::from),
+            PixelFormat::Bgr8 => {
+                for pixel in data.chunks_exact_mut(3) {
+                    pixel.swap(0, 2);
+                }
+                RgbImage::from_vec(width, height, data).map(DynamicImage::from)
+            }
+            PixelFormat::Gray8 => GrayImage::from_vec(width, height, data).map(DynamicImage::from),
+        };
+        image.ok_or(WincError::SizeMismatch { expected, actual })
+    }
+}
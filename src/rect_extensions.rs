@@ -1,5 +1,9 @@
+#[cfg(windows)]
 use windows::Win32::Foundation::RECT;
 
+use crate::prelude::Point;
+use crate::prelude::Rect;
+
 pub trait HasLeft {
     fn left(&self) -> i32;
 }
@@ -12,21 +16,45 @@ pub trait HasTop {
 pub trait HasBottom {
     fn bottom(&self) -> i32;
 }
+impl HasLeft for Rect {
+    fn left(&self) -> i32 {
+        self.left
+    }
+}
+#[cfg(windows)]
 impl HasLeft for RECT {
     fn left(&self) -> i32 {
         self.left
     }
 }
+impl HasRight for Rect {
+    fn right(&self) -> i32 {
+        self.right
+    }
+}
+#[cfg(windows)]
 impl HasRight for RECT {
     fn right(&self) -> i32 {
         self.right
     }
 }
+impl HasTop for Rect {
+    fn top(&self) -> i32 {
+        self.top
+    }
+}
+#[cfg(windows)]
 impl HasTop for RECT {
     fn top(&self) -> i32 {
         self.top
     }
 }
+impl HasBottom for Rect {
+    fn bottom(&self) -> i32 {
+        self.bottom
+    }
+}
+#[cfg(windows)]
 impl HasBottom for RECT {
     fn bottom(&self) -> i32 {
         self.bottom
@@ -58,75 +86,88 @@ where
 }
 
 pub trait HasTopLeft {
-    fn top_left(&self) -> (i32, i32);
+    fn top_left(&self) -> Point;
 }
 pub trait HasTopRight {
-    fn top_right(&self) -> (i32, i32);
+    fn top_right(&self) -> Point;
 }
 pub trait HasBottomLeft {
-    fn bottom_left(&self) -> (i32, i32);
+    fn bottom_left(&self) -> Point;
 }
 pub trait HasBottomRight {
-    fn bottom_right(&self) -> (i32, i32);
+    fn bottom_right(&self) -> Point;
 }
 impl<T> HasTopLeft for T
 where
     T: HasTop + HasLeft,
 {
-    fn top_left(&self) -> (i32, i32) {
-        (self.left(), self.top())
+    fn top_left(&self) -> Point {
+        Point::new(self.left(), self.top())
     }
 }
 impl<T> HasTopRight for T
 where
     T: HasTop + HasRight,
 {
-    fn top_right(&self) -> (i32, i32) {
-        (self.right(), self.top())
+    fn top_right(&self) -> Point {
+        Point::new(self.right(), self.top())
     }
 }
 impl<T> HasBottomLeft for T
 where
     T: HasBottom + HasLeft,
 {
-    fn bottom_left(&self) -> (i32, i32) {
-        (self.left(), self.bottom())
+    fn bottom_left(&self) -> Point {
+        Point::new(self.left(), self.bottom())
     }
 }
 impl<T> HasBottomRight for T
 where
     T: HasBottom + HasRight,
 {
-    fn bottom_right(&self) -> (i32, i32) {
-        (self.right(), self.bottom())
+    fn bottom_right(&self) -> Point {
+        Point::new(self.right(), self.bottom())
     }
 }
 
 pub trait FromCorners {
-    fn from_corners(p0: (i32, i32), p1: (i32, i32)) -> Self;
+    fn from_corners(p0: impl Into<Point>, p1: impl Into<Point>) -> Self;
 }
-impl FromCorners for RECT {
-    fn from_corners(p0: (i32, i32), p1: (i32, i32)) -> Self {
-        RECT {
-            left: p0.0.min(p1.0),
-            top: p0.1.min(p1.1),
-            right: p1.0.max(p0.0),
-            bottom: p1.1.max(p0.1),
+impl FromCorners for Rect {
+    fn from_corners(p0: impl Into<Point>, p1: impl Into<Point>) -> Self {
+        let (p0, p1) = (p0.into(), p1.into());
+        Rect {
+            left: p0.x.min(p1.x),
+            top: p0.y.min(p1.y),
+            right: p1.x.max(p0.x),
+            bottom: p1.y.max(p0.y),
         }
     }
 }
+#[cfg(windows)]
+impl FromCorners for RECT {
+    fn from_corners(p0: impl Into<Point>, p1: impl Into<Point>) -> Self {
+        Rect::from_corners(p0, p1).into()
+    }
+}
 
 pub trait Translatable {
     fn translate(&self, dx: i32, dy: i32) -> Self;
 }
+impl Translatable for Rect {
+    fn translate(&self, dx: i32, dy: i32) -> Self {
+        *self + Point::new(dx, dy)
+    }
+}
+#[cfg(windows)]
 impl Translatable for RECT {
     fn translate(&self, dx: i32, dy: i32) -> Self {
-        RECT {
-            left: self.left + dx,
-            top: self.top + dy,
-            right: self.right + dx,
-            bottom: self.bottom + dy,
-        }
+        Rect::from(*self).translate(dx, dy).into()
+    }
+}
+impl Translatable for Point {
+    fn translate(&self, dx: i32, dy: i32) -> Self {
+        *self + Point::new(dx, dy)
     }
 }
 impl Translatable for (i32, i32) {
@@ -134,3 +175,316 @@ impl Translatable for (i32, i32) {
         (self.0 + dx, self.1 + dy)
     }
 }
+
+/// Right and bottom edges are exclusive, so a rect with no width or height covers no pixels
+pub trait IsEmpty {
+    fn is_empty(&self) -> bool;
+}
+impl<T> IsEmpty for T
+where
+    T: HasWidth + HasHeight,
+{
+    fn is_empty(&self) -> bool {
+        self.width() <= 0 || self.height() <= 0
+    }
+}
+
+pub trait HasArea {
+    /// The number of pixels covered, 0 when empty. Wide so a virtual desktop can't overflow it.
+    fn area(&self) -> i64;
+}
+impl<T> HasArea for T
+where
+    T: HasWidth + HasHeight,
+{
+    fn area(&self) -> i64 {
+        self.width().max(0) as i64 * self.height().max(0) as i64
+    }
+}
+
+pub trait ContainsPoint {
+    fn contains_point(&self, point: impl Into<Point>) -> bool;
+}
+impl<T> ContainsPoint for T
+where
+    T: HasLeft + HasTop + HasRight + HasBottom,
+{
+    fn contains_point(&self, point: impl Into<Point>) -> bool {
+        let point = point.into();
+        point.x >= self.left()
+            && point.x < self.right()
+            && point.y >= self.top()
+            && point.y < self.bottom()
+    }
+}
+
+pub trait ContainsRect {
+    /// Whether every pixel of `other` is inside, which an empty `other` always is
+    fn contains_rect(&self, other: &Self) -> bool;
+}
+impl<T> ContainsRect for T
+where
+    T: HasLeft + HasTop + HasRight + HasBottom,
+{
+    fn contains_rect(&self, other: &Self) -> bool {
+        other.is_empty()
+            || (other.left() >= self.left()
+                && other.top() >= self.top()
+                && other.right() <= self.right()
+                && other.bottom() <= self.bottom())
+    }
+}
+
+pub trait Intersect: Sized {
+    /// The pixels both rects cover, `None` when they don't overlap
+    fn intersection(&self, other: &Self) -> Option<Self>;
+}
+impl Intersect for Rect {
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        let rect = Rect::new(
+            self.left.max(other.left),
+            self.top.max(other.top),
+            self.right.min(other.right),
+            self.bottom.min(other.bottom),
+        );
+        (!rect.is_empty()).then_some(rect)
+    }
+}
+#[cfg(windows)]
+impl Intersect for RECT {
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        Rect::from(*self)
+            .intersection(&Rect::from(*other))
+            .map(Into::into)
+    }
+}
+
+pub trait Union {
+    /// The smallest rect covering both, ignoring empty rects
+    fn union(&self, other: &Self) -> Self;
+}
+impl Union for Rect {
+    fn union(&self, other: &Self) -> Self {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        Rect::new(
+            self.left.min(other.left),
+            self.top.min(other.top),
+            self.right.max(other.right),
+            self.bottom.max(other.bottom),
+        )
+    }
+}
+#[cfg(windows)]
+impl Union for RECT {
+    fn union(&self, other: &Self) -> Self {
+        Rect::from(*self).union(&Rect::from(*other)).into()
+    }
+}
+
+pub trait Subtract: Sized {
+    /// The pixels not covered by `other`, as up to 4 non overlapping rects.
+    ///
+    /// Full width bands above and below `other`, then the pieces left and right of it.
+    fn subtract(&self, other: &Self) -> Vec<Self>;
+}
+impl Subtract for Rect {
+    fn subtract(&self, other: &Self) -> Vec<Self> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let Some(hole) = self.intersection(other) else {
+            return vec![*self];
+        };
+        let pieces = [
+            Rect::new(self.left, self.top, self.right, hole.top),
+            Rect::new(self.left, hole.bottom, self.right, self.bottom),
+            Rect::new(self.left, hole.top, hole.left, hole.bottom),
+            Rect::new(hole.right, hole.top, self.right, hole.bottom),
+        ];
+        pieces.into_iter().filter(|rect| !rect.is_empty()).collect()
+    }
+}
+#[cfg(windows)]
+impl Subtract for RECT {
+    fn subtract(&self, other: &Self) -> Vec<Self> {
+        Rect::from(*self)
+            .subtract(&Rect::from(*other))
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+}
+
+pub trait ClampTo {
+    /// Move every edge inside `bounds`, so a rect outside of it collapses to an empty one on its border
+    fn clamp_to(&self, bounds: &Self) -> Self;
+}
+impl ClampTo for Rect {
+    fn clamp_to(&self, bounds: &Self) -> Self {
+        let clamp_x = |x: i32| x.clamp(bounds.left, bounds.right.max(bounds.left));
+        let clamp_y = |y: i32| y.clamp(bounds.top, bounds.bottom.max(bounds.top));
+        Rect::new(
+            clamp_x(self.left),
+            clamp_y(self.top),
+            clamp_x(self.right),
+            clamp_y(self.bottom),
+        )
+    }
+}
+#[cfg(windows)]
+impl ClampTo for RECT {
+    fn clamp_to(&self, bounds: &Self) -> Self {
+        Rect::from(*self).clamp_to(&Rect::from(*bounds)).into()
+    }
+}
+
+pub trait Inflate {
+    /// Grow every side by `dx` horizontally and `dy` vertically, negative amounts shrink, like `InflateRect`
+    fn inflate(&self, dx: i32, dy: i32) -> Self;
+}
+impl Inflate for Rect {
+    fn inflate(&self, dx: i32, dy: i32) -> Self {
+        Rect::new(
+            self.left - dx,
+            self.top - dy,
+            self.right + dx,
+            self.bottom + dy,
+        )
+    }
+}
+#[cfg(windows)]
+impl Inflate for RECT {
+    fn inflate(&self, dx: i32, dy: i32) -> Self {
+        Rect::from(*self).inflate(dx, dy).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn rect() -> impl Strategy<Value = Rect> {
+        // small coordinates so random rects overlap often, and some are empty or inverted
+        (-20..20, -20..20, -5..25, -5..25).prop_map(|(x, y, w, h)| Rect::new(x, y, x + w, y + h))
+    }
+
+    fn point() -> impl Strategy<Value = Point> {
+        (-30..50, -30..50).prop_map(|(x, y)| Point::new(x, y))
+    }
+
+    #[test]
+    fn examples() {
+        let rect = Rect::new(0, 0, 10, 10);
+        assert_eq!(rect.area(), 100);
+        assert!(rect.contains_point((0, 0)));
+        assert!(!rect.contains_point((10, 5)));
+        assert_eq!(
+            rect.intersection(&Rect::new(5, -5, 15, 5)),
+            Some(Rect::new(5, 0, 10, 5))
+        );
+        assert_eq!(rect.intersection(&Rect::new(10, 0, 20, 10)), None);
+        assert_eq!(
+            rect.union(&Rect::new(-5, 20, 0, 25)),
+            Rect::new(-5, 0, 10, 25)
+        );
+        assert_eq!(
+            rect.subtract(&Rect::new(2, 2, 8, 8)),
+            vec![
+                Rect::new(0, 0, 10, 2),
+                Rect::new(0, 8, 10, 10),
+                Rect::new(0, 2, 2, 8),
+                Rect::new(8, 2, 10, 8),
+            ]
+        );
+        assert_eq!(rect.subtract(&rect.inflate(1, 1)), vec![]);
+        assert_eq!(
+            Rect::new(-5, 5, 20, 30).clamp_to(&rect),
+            Rect::new(0, 5, 10, 10)
+        );
+        assert!(Rect::new(20, 20, 30, 30).clamp_to(&rect).is_empty());
+        assert_eq!(rect.inflate(-5, -5).area(), 0);
+        assert_eq!(
+            Rect::new(0, 0, i32::MAX, i32::MAX).area(),
+            i32::MAX as i64 * i32::MAX as i64
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn intersection_is_what_both_contain(a in rect(), b in rect(), p in point()) {
+            let inside = a.intersection(&b).is_some_and(|i| i.contains_point(p));
+            prop_assert_eq!(inside, a.contains_point(p) && b.contains_point(p));
+            prop_assert_eq!(a.intersection(&b), b.intersection(&a));
+        }
+
+        #[test]
+        fn union_contains_both(a in rect(), b in rect(), p in point()) {
+            let union = a.union(&b);
+            prop_assert!(union.contains_rect(&a));
+            prop_assert!(union.contains_rect(&b));
+            if a.contains_point(p) || b.contains_point(p) {
+                prop_assert!(union.contains_point(p));
+            }
+            prop_assert!(union.area() >= a.area().max(b.area()));
+        }
+
+        #[test]
+        fn subtract_covers_the_rest_exactly(a in rect(), b in rect(), p in point()) {
+            let pieces = a.subtract(&b);
+            prop_assert!(pieces.len() <= 4);
+            let covering = pieces.iter().filter(|piece| piece.contains_point(p)).count();
+            let expected = a.contains_point(p) && !b.contains_point(p);
+            prop_assert_eq!(covering, expected as usize);
+            let overlap = a.intersection(&b).map_or(0, |i| i.area());
+            let area: i64 = pieces.iter().map(|piece| piece.area()).sum();
+            prop_assert_eq!(area + overlap, a.area());
+            for piece in &pieces {
+                prop_assert!(!piece.is_empty());
+                prop_assert!(a.contains_rect(piece));
+            }
+        }
+
+        #[test]
+        fn clamp_stays_inside(a in rect(), bounds in rect()) {
+            let clamped = a.clamp_to(&bounds);
+            if !bounds.is_empty() {
+                prop_assert!(bounds.contains_rect(&clamped));
+            }
+            if bounds.contains_rect(&a) && !a.is_empty() {
+                prop_assert_eq!(clamped, a);
+            }
+            if !a.is_empty() {
+                prop_assert_eq!(clamped.is_empty(), a.intersection(&bounds).is_none());
+            }
+        }
+
+        #[test]
+        fn inflate_round_trips(a in rect(), dx in -10..10, dy in -10..10) {
+            let inflated = a.inflate(dx, dy);
+            prop_assert_eq!(inflated.inflate(-dx, -dy), a);
+            prop_assert_eq!(inflated.width(), a.width() + 2 * dx);
+            if dx >= 0 && dy >= 0 {
+                prop_assert!(inflated.contains_rect(&a));
+            }
+        }
+
+        #[test]
+        fn area_counts_contained_points(a in rect()) {
+            let mut count = 0;
+            for y in -30..50 {
+                for x in -30..50 {
+                    count += a.contains_point((x, y)) as i64;
+                }
+            }
+            prop_assert_eq!(count, a.area());
+            prop_assert_eq!(a.is_empty(), a.area() == 0);
+        }
+    }
+}
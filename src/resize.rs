@@ -0,0 +1,609 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+use crate::prelude::check_buffer_size;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::CaptureSource;
+use crate::prelude::Metrics;
+use crate::prelude::MonitorInfo;
+use crate::prelude::Rect;
+use crate::prelude::Size;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+
+/// How source pixels are combined into each output pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ResizeFilter {
+    /// The closest source pixel, fastest and blocky
+    Nearest,
+    /// Linear blend of the closest pixels, widened when downscaling so nothing is skipped
+    #[default]
+    Bilinear,
+    /// Average of the source pixels each output pixel covers, best for downscaling text
+    Area,
+    /// Sharpest, at the cost of slight ringing around hard edges
+    Lanczos3,
+}
+
+/// The filters that weigh source pixels by their distance from the output pixel's center
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kernel {
+    Triangle,
+    Lanczos3,
+}
+
+impl Kernel {
+    /// How far the kernel reaches, in source pixels at 1:1
+    fn radius(self) -> f32 {
+        match self {
+            Kernel::Triangle => 1.0,
+            Kernel::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Kernel::Triangle => (1.0 - x).max(0.0),
+            Kernel::Lanczos3 => {
+                if x >= 3.0 {
+                    0.0
+                } else {
+                    sinc(x) * sinc(x / 3.0)
+                }
+            }
+        }
+    }
+
+    fn contribution(self, i: usize, scale: f32, src_len: usize) -> Contribution {
+        // widen the kernel when downscaling so every source pixel contributes
+        let filter_scale = scale.max(1.0);
+        let support = self.radius() * filter_scale;
+        let center = (i as f32 + 0.5) * scale;
+        let start = ((center - support).floor().max(0.0) as usize).min(src_len - 1);
+        let end = ((center + support).ceil() as usize).clamp(start + 1, src_len);
+        let weights = (start..end)
+            .map(|j| self.weight((j as f32 + 0.5 - center) / filter_scale))
+            .collect();
+        normalized(start, weights)
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// The source pixels contributing to one output pixel along an axis
+#[derive(Debug, Clone, PartialEq)]
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+fn contributions(src_len: usize, dst_len: usize, filter: ResizeFilter) -> Vec<Contribution> {
+    let scale = src_len as f32 / dst_len as f32;
+    (0..dst_len)
+        .map(|i| match filter {
+            ResizeFilter::Nearest => Contribution {
+                start: (((i as f32 + 0.5) * scale) as usize).min(src_len - 1),
+                weights: vec![1.0],
+            },
+            ResizeFilter::Area => {
+                // exact coverage of [i, i + 1) in output pixels by each source pixel
+                let (left, right) = (i as f32 * scale, (i + 1) as f32 * scale);
+                let start = (left.floor() as usize).min(src_len - 1);
+                let end = (right.ceil() as usize).clamp(start + 1, src_len);
+                let weights = (start..end)
+                    .map(|j| (right.min((j + 1) as f32) - left.max(j as f32)).max(0.0))
+                    .collect();
+                normalized(start, weights)
+            }
+            ResizeFilter::Bilinear => Kernel::Triangle.contribution(i, scale, src_len),
+            ResizeFilter::Lanczos3 => Kernel::Lanczos3.contribution(i, scale, src_len),
+        })
+        .collect()
+}
+
+fn normalized(start: usize, mut weights: Vec<f32>) -> Contribution {
+    let sum: f32 = weights.iter().sum();
+    if sum != 0.0 {
+        weights.iter_mut().for_each(|weight| *weight /= sum);
+    }
+    Contribution { start, weights }
+}
+
+/// Resize 4 channel pixels, such as BGRA or RGBA, from `src_size` to `dst_size`.
+///
+/// Filters horizontally then vertically, every channel including alpha is filtered the same way.
+pub fn resize_bgra(
+    src: &[u8],
+    src_size: Size,
+    dst: &mut [u8],
+    dst_size: Size,
+    filter: ResizeFilter,
+) -> WincResult<()> {
+    let identity = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    resize_mapped(
+        src,
+        src_size,
+        dst,
+        dst_size,
+        filter,
+        identity_table(),
+        identity,
+    )
+}
+
+/// [`resize_bgra`] in linear light, so averaging thin dark strokes on a light background doesn't darken them.
+///
+/// Colors are converted from sRGB to linear before filtering and back afterwards, alpha is left as is.
+pub fn resize_bgra_linear(
+    src: &[u8],
+    src_size: Size,
+    dst: &mut [u8],
+    dst_size: Size,
+    filter: ResizeFilter,
+) -> WincResult<()> {
+    let table = linear_to_srgb_table();
+    let encode = |value: f32| {
+        let index = (value * (LINEAR_STEPS - 1) as f32).round();
+        table[index.clamp(0.0, (LINEAR_STEPS - 1) as f32) as usize]
+    };
+    resize_mapped(
+        src,
+        src_size,
+        dst,
+        dst_size,
+        filter,
+        srgb_to_linear_table(),
+        encode,
+    )
+}
+
+/// How many linear values the encode table has, enough to be within 1 of the exact sRGB value
+const LINEAR_STEPS: usize = 4096;
+
+/// sRGB transfer function, from 0..=1 encoded to 0..=1 linear light
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Inverse of [`srgb_to_linear`]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn identity_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| i as f32))
+}
+
+fn srgb_to_linear_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))
+}
+
+fn linear_to_srgb_table() -> &'static [u8; LINEAR_STEPS] {
+    static TABLE: OnceLock<[u8; LINEAR_STEPS]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let srgb = linear_to_srgb(i as f32 / (LINEAR_STEPS - 1) as f32);
+            (srgb * 255.0).round().clamp(0.0, 255.0) as u8
+        })
+    })
+}
+
+/// Resize with colors looked up in `decode` before filtering and passed through `encode` afterwards
+fn resize_mapped(
+    src: &[u8],
+    src_size: Size,
+    dst: &mut [u8],
+    dst_size: Size,
+    filter: ResizeFilter,
+    decode: &[f32; 256],
+    encode: impl Fn(f32) -> u8,
+) -> WincResult<()> {
+    let (src_width, src_height) = (
+        src_size.width.max(0) as usize,
+        src_size.height.max(0) as usize,
+    );
+    let (dst_width, dst_height) = (
+        dst_size.width.max(0) as usize,
+        dst_size.height.max(0) as usize,
+    );
+    check_buffer_size(src_width * src_height * 4, src.len())?;
+    check_buffer_size(dst_width * dst_height * 4, dst.len())?;
+    if dst.is_empty() {
+        return Ok(());
+    }
+    if src.is_empty() {
+        return Err(WincError::Unsupported(format!(
+            "cannot resize a {}x{} image to {}x{}",
+            src_width, src_height, dst_width, dst_height
+        )));
+    }
+
+    let columns = contributions(src_width, dst_width, filter);
+    let mut horizontal = vec![0f32; dst_width * src_height * 4];
+    for (src_row, row) in src
+        .chunks_exact(src_width * 4)
+        .zip(horizontal.chunks_exact_mut(dst_width * 4))
+    {
+        for (column, out) in columns.iter().zip(row.chunks_exact_mut(4)) {
+            let mut sum = [0f32; 4];
+            for (k, weight) in column.weights.iter().enumerate() {
+                let pixel = &src_row[(column.start + k) * 4..][..4];
+                sum[0] += decode[pixel[0] as usize] * weight;
+                sum[1] += decode[pixel[1] as usize] * weight;
+                sum[2] += decode[pixel[2] as usize] * weight;
+                sum[3] += pixel[3] as f32 * weight;
+            }
+            out.copy_from_slice(&sum);
+        }
+    }
+
+    let rows = contributions(src_height, dst_height, filter);
+    for (row, dst_row) in rows.iter().zip(dst.chunks_exact_mut(dst_width * 4)) {
+        for (x, out) in dst_row.chunks_exact_mut(4).enumerate() {
+            let mut sum = [0f32; 4];
+            for (k, weight) in row.weights.iter().enumerate() {
+                let pixel = &horizontal[((row.start + k) * dst_width + x) * 4..][..4];
+                for channel in 0..4 {
+                    sum[channel] += pixel[channel] * weight;
+                }
+            }
+            // encode and the alpha clamp also catch lanczos overshooting around hard edges
+            out[0] = encode(sum[0]);
+            out[1] = encode(sum[1]);
+            out[2] = encode(sum[2]);
+            out[3] = sum[3].round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    Ok(())
+}
+
+/// Captures another source and resizes every frame to a fixed size
+pub struct ScaledCapturer<S> {
+    pub source: S,
+    pub size: Size,
+    pub filter: ResizeFilter,
+    /// Filter in linear light, see [`resize_bgra_linear`]
+    pub linear_light: bool,
+    /// The full size capture, kept between frames
+    scratch: RefCell<Vec<u8>>,
+}
+
+/// Wrap `source` so its frames come out at `size`, whatever the size of its region
+pub fn get_scaled_capturer<S: CaptureSource>(
+    source: S,
+    size: Size,
+    filter: ResizeFilter,
+) -> ScaledCapturer<S> {
+    ScaledCapturer {
+        source,
+        size,
+        filter,
+        linear_light: false,
+        scratch: RefCell::default(),
+    }
+}
+
+impl<S> ScaledCapturer<S> {
+    pub fn with_linear_light(mut self, linear_light: bool) -> Self {
+        self.linear_light = linear_light;
+        self
+    }
+}
+
+impl<S: CaptureSource> CaptureSource for ScaledCapturer<S> {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        let source_size = self.source.size();
+        if source_size == self.size {
+            return self.source.capture_bgra_into(buffer, metrics);
+        }
+        let mut scratch = self.scratch.borrow_mut();
+        scratch.resize(
+            source_size.width as usize * source_size.height as usize * 4,
+            0,
+        );
+        self.source.capture_bgra_into(&mut scratch, metrics)?;
+        metrics.begin("resize");
+        let result = if self.linear_light {
+            resize_bgra_linear(&scratch, source_size, buffer, self.size, self.filter)
+        } else {
+            resize_bgra(&scratch, source_size, buffer, self.size, self.filter)
+        };
+        metrics.end("resize");
+        result
+    }
+
+    fn region(&self) -> Rect {
+        self.source.region()
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        self.source.monitor_info()
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        self.source.alpha_policy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [ResizeFilter; 4] = [
+        ResizeFilter::Nearest,
+        ResizeFilter::Bilinear,
+        ResizeFilter::Area,
+        ResizeFilter::Lanczos3,
+    ];
+
+    fn resized(src: &[u8], src_size: Size, dst_size: Size, filter: ResizeFilter) -> Vec<u8> {
+        let mut dst = vec![0; (dst_size.width * dst_size.height * 4) as usize];
+        resize_bgra(src, src_size, &mut dst, dst_size, filter).unwrap();
+        dst
+    }
+
+    #[test]
+    fn same_size_is_a_copy() {
+        let src: Vec<u8> = (0..7 * 5 * 4).map(|i| (i * 31 % 256) as u8).collect();
+        for filter in FILTERS {
+            let size = Size::new(7, 5);
+            assert_eq!(resized(&src, size, size, filter), src, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn solid_stays_solid() {
+        let src = [10, 200, 30, 255].repeat(37 * 23);
+        for filter in FILTERS {
+            for dst_size in [Size::new(5, 3), Size::new(80, 61), Size::new(1, 1)] {
+                let dst = resized(&src, Size::new(37, 23), dst_size, filter);
+                assert!(
+                    dst.chunks_exact(4).all(|p| p == [10, 200, 30, 255]),
+                    "{:?} {:?}",
+                    filter,
+                    dst_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn area_averages_blocks() {
+        // 4x2 to 2x1, each output is the mean of a 2x2 block
+        let src = [
+            [0, 0, 0, 255],
+            [100, 100, 100, 255],
+            [10, 20, 30, 255],
+            [30, 20, 10, 255],
+            [100, 100, 100, 255],
+            [200, 200, 200, 255],
+            [10, 20, 30, 255],
+            [30, 20, 10, 255],
+        ]
+        .concat();
+        let dst = resized(&src, Size::new(4, 2), Size::new(2, 1), ResizeFilter::Area);
+        assert_eq!(dst, [100, 100, 100, 255, 20, 20, 20, 255]);
+    }
+
+    #[test]
+    fn nearest_picks_pixels() {
+        let src: Vec<u8> = (0..4u8).flat_map(|i| [i, i, i, 255]).collect();
+        let dst = resized(
+            &src,
+            Size::new(4, 1),
+            Size::new(2, 1),
+            ResizeFilter::Nearest,
+        );
+        assert_eq!(dst, [1, 1, 1, 255, 3, 3, 3, 255]);
+        let dst = resized(
+            &src,
+            Size::new(4, 1),
+            Size::new(8, 1),
+            ResizeFilter::Nearest,
+        );
+        assert_eq!(
+            dst.chunks_exact(4).map(|p| p[0]).collect::<Vec<_>>(),
+            [0, 0, 1, 1, 2, 2, 3, 3]
+        );
+    }
+
+    #[test]
+    fn upscaled_gradient_is_monotonic() {
+        let src: Vec<u8> = (0..8u8).flat_map(|i| [i * 30, 0, 0, 255]).collect();
+        for filter in [ResizeFilter::Bilinear, ResizeFilter::Area] {
+            let dst = resized(&src, Size::new(8, 1), Size::new(29, 1), filter);
+            let values: Vec<u8> = dst.chunks_exact(4).map(|p| p[0]).collect();
+            assert!(values.windows(2).all(|w| w[0] <= w[1]), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn lanczos_clamps_ringing() {
+        // a hard edge makes lanczos overshoot, which must clamp instead of wrapping
+        let src: Vec<u8> = (0..16)
+            .flat_map(|i| if i < 8 { [0, 0, 0, 255] } else { [255; 4] })
+            .collect();
+        let dst = resized(
+            &src,
+            Size::new(16, 1),
+            Size::new(40, 1),
+            ResizeFilter::Lanczos3,
+        );
+        let values: Vec<u8> = dst.chunks_exact(4).map(|p| p[0]).collect();
+        assert_eq!(values[0], 0);
+        assert_eq!(values[39], 255);
+        assert!(values[..15].iter().all(|&v| v < 20));
+        assert!(values[25..].iter().all(|&v| v > 235));
+    }
+
+    /// Slow but exact linear light resize, to check the lookup tables against
+    fn reference_linear(
+        src: &[u8],
+        src_size: Size,
+        dst_size: Size,
+        filter: ResizeFilter,
+    ) -> Vec<u8> {
+        let (src_width, dst_width) = (src_size.width as usize, dst_size.width as usize);
+        let columns = contributions(src_width, dst_width, filter);
+        let rows = contributions(src_size.height as usize, dst_size.height as usize, filter);
+        let mut dst = Vec::new();
+        for row in &rows {
+            for column in &columns {
+                let mut sum = [0f64; 4];
+                for (ky, wy) in row.weights.iter().enumerate() {
+                    for (kx, wx) in column.weights.iter().enumerate() {
+                        let i = ((row.start + ky) * src_width + column.start + kx) * 4;
+                        for channel in 0..4 {
+                            let value = src[i + channel] as f64 / 255.0;
+                            let value = if channel == 3 {
+                                value
+                            } else {
+                                srgb_to_linear(value as f32) as f64
+                            };
+                            sum[channel] += value * (*wy as f64) * (*wx as f64);
+                        }
+                    }
+                }
+                for (channel, value) in sum.iter().enumerate() {
+                    let value = if channel == 3 {
+                        *value
+                    } else {
+                        linear_to_srgb(*value as f32) as f64
+                    };
+                    dst.push((value * 255.0).round().clamp(0.0, 255.0) as u8);
+                }
+            }
+        }
+        dst
+    }
+
+    /// Black 1 pixel strokes on white, like small text
+    fn strokes(size: Size) -> Vec<u8> {
+        (0..size.width * size.height)
+            .flat_map(|i| {
+                let (x, y) = (i % size.width, i / size.width);
+                if x % 2 == 0 || y % 7 == 3 {
+                    [0, 0, 0, 255]
+                } else {
+                    [255; 4]
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn linear_light_keeps_strokes_bright() {
+        let src = strokes(Size::new(8, 2));
+        let mut srgb = vec![0; 4 * 4];
+        let mut linear = vec![0; 4 * 4];
+        let dst_size = Size::new(4, 1);
+        resize_bgra(
+            &src,
+            Size::new(8, 2),
+            &mut srgb,
+            dst_size,
+            ResizeFilter::Area,
+        )
+        .unwrap();
+        resize_bgra_linear(
+            &src,
+            Size::new(8, 2),
+            &mut linear,
+            dst_size,
+            ResizeFilter::Area,
+        )
+        .unwrap();
+        // half black half white is 50% linear light, which is 188 in sRGB rather than 128
+        assert_eq!(&srgb[..4], &[128, 128, 128, 255]);
+        assert_eq!(&linear[..4], &[188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn linear_light_matches_reference() {
+        let src_size = Size::new(61, 29);
+        let mut src = strokes(src_size);
+        for (i, byte) in src.iter_mut().enumerate() {
+            if i % 4 != 3 && i % 5 == 0 {
+                *byte = (i * 7 % 256) as u8;
+            }
+        }
+        for filter in FILTERS {
+            for dst_size in [Size::new(20, 10), Size::new(7, 29), Size::new(90, 40)] {
+                let mut dst = vec![0; (dst_size.width * dst_size.height * 4) as usize];
+                resize_bgra_linear(&src, src_size, &mut dst, dst_size, filter).unwrap();
+                let expected = reference_linear(&src, src_size, dst_size, filter);
+                for (a, b) in dst.iter().zip(expected.iter()) {
+                    assert!(
+                        (*a as i32 - *b as i32).abs() <= 1,
+                        "{:?} {} vs {}",
+                        filter,
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linear_tables_round_trip() {
+        let table = linear_to_srgb_table();
+        for (i, linear) in srgb_to_linear_table().iter().enumerate() {
+            let index = (linear * (LINEAR_STEPS - 1) as f32).round() as usize;
+            assert!((table[index] as i32 - i as i32).abs() <= 1, "{}", i);
+        }
+    }
+
+    #[test]
+    fn checks_sizes() {
+        let mut dst = vec![0; 16];
+        assert!(matches!(
+            resize_bgra(
+                &[0; 15],
+                Size::new(2, 2),
+                &mut dst,
+                Size::new(2, 2),
+                ResizeFilter::Area
+            ),
+            Err(WincError::SizeMismatch { .. })
+        ));
+        assert!(resize_bgra(
+            &[],
+            Size::new(0, 0),
+            &mut dst,
+            Size::new(2, 2),
+            ResizeFilter::Area
+        )
+        .is_err());
+        assert!(resize_bgra(
+            &[0; 16],
+            Size::new(2, 2),
+            &mut [],
+            Size::new(0, 0),
+            ResizeFilter::Area
+        )
+        .is_ok());
+    }
+}
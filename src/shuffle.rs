@@ -1,29 +1,1000 @@
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::int32x4_t;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::uint16x4_t;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::uint8x16_t;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vaddq_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vcombine_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vcombine_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vdupq_n_s32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vdupq_n_u32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vget_high_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vget_high_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vget_low_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vget_low_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vld4q_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vmlal_n_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vmlaq_n_s32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vmovl_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vmovl_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vpaddlq_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vqmovn_u16;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vqmovn_u32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vqmovun_s32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vreinterpretq_s32_u32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vshrq_n_s32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vshrq_n_u32;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vst1_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vst1q_u8;
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vst4q_u8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::__m128i;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::__m256i;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm256_loadu_si256;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm256_setr_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm256_shuffle_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm256_storeu_si256;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_add_epi16;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_add_epi32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_cvtsi128_si32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_hadd_epi32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_loadu_si128;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_madd_epi16;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_packs_epi32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_packus_epi16;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_set1_epi32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_setr_epi16;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_setr_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_setzero_si128;
 #[cfg(target_arch = "x86")]
 use std::arch::x86::_mm_shuffle_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_srai_epi32;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_srli_si128;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_storeu_si128;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_unpackhi_epi8;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_unpacklo_epi64;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_mm_unpacklo_epi8;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::__m128i;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::__m256i;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm256_loadu_si256;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm256_setr_epi8;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm256_shuffle_epi8;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm256_storeu_si256;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_add_epi16;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_add_epi32;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_cvtsi128_si32;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_hadd_epi32;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::_mm_loadu_si128;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_madd_epi16;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_packs_epi32;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_packus_epi16;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_set1_epi32;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_setr_epi16;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::_mm_setr_epi8;
 #[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_setzero_si128;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::_mm_shuffle_epi8;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_srai_epi32;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_srli_si128;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::_mm_storeu_si128;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_unpackhi_epi8;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_unpacklo_epi64;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_mm_unpacklo_epi8;
+
+use crate::prelude::check_buffer_size;
+use crate::prelude::WincResult;
 
 /// Convert BGRA to RGBA
 ///
-/// Uses SIMD to go fast
+/// Uses the widest SIMD the CPU supports to go fast.
+/// Trailing bytes that don't make up a whole pixel are left untouched.
 pub fn bgra_to_rgba(data: &mut [u8]) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { bgra_to_rgba_avx2(data) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            return unsafe { bgra_to_rgba_ssse3(data) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // NEON is mandatory on aarch64
+        return unsafe { bgra_to_rgba_neon(data) };
+    }
+    #[allow(unreachable_code)]
+    bgra_to_rgba_scalar(data)
+}
+
+/// Convert BGRA to RGBA one pixel at a time
+pub fn bgra_to_rgba_scalar(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+}
+
+/// Convert BGRA to RGBA 4 pixels at a time
+///
+/// # Safety
+///
+/// The CPU must support SSSE3
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "ssse3")]
+pub unsafe fn bgra_to_rgba_ssse3(data: &mut [u8]) {
     // The shuffle mask for converting BGRA -> RGBA
-    let mask: __m128i = unsafe {
-        _mm_setr_epi8(
-            2, 1, 0, 3, // First pixel
-            6, 5, 4, 7, // Second pixel
-            10, 9, 8, 11, // Third pixel
-            14, 13, 12, 15, // Fourth pixel
+    let mask: __m128i = _mm_setr_epi8(
+        2, 1, 0, 3, // First pixel
+        6, 5, 4, 7, // Second pixel
+        10, 9, 8, 11, // Third pixel
+        14, 13, 12, 15, // Fourth pixel
+    );
+    let mut chunks = data.chunks_exact_mut(16);
+    for chunk in &mut chunks {
+        let mut vector = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+        vector = _mm_shuffle_epi8(vector, mask);
+        _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, vector);
+    }
+    bgra_to_rgba_scalar(chunks.into_remainder());
+}
+
+/// Convert BGRA to RGBA 8 pixels at a time
+///
+/// # Safety
+///
+/// The CPU must support AVX2
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+pub unsafe fn bgra_to_rgba_avx2(data: &mut [u8]) {
+    // the shuffle works within each 128 bit lane, so the mask is the SSSE3 one twice
+    let mask: __m256i = _mm256_setr_epi8(
+        2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8, 11, 14, 13, 12, 15, // First lane
+        2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8, 11, 14, 13, 12, 15, // Second lane
+    );
+    let mut chunks = data.chunks_exact_mut(32);
+    for chunk in &mut chunks {
+        let mut vector = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+        vector = _mm256_shuffle_epi8(vector, mask);
+        _mm256_storeu_si256(chunk.as_mut_ptr() as *mut __m256i, vector);
+    }
+    bgra_to_rgba_ssse3(chunks.into_remainder());
+}
+
+/// Convert BGRA to RGBA 16 pixels at a time
+///
+/// # Safety
+///
+/// The CPU must support NEON, which every aarch64 CPU does
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+pub unsafe fn bgra_to_rgba_neon(data: &mut [u8]) {
+    let mut chunks = data.chunks_exact_mut(64);
+    for chunk in &mut chunks {
+        // deinterleave into one register per channel, swap blue and red, interleave back
+        let mut channels = vld4q_u8(chunk.as_ptr());
+        std::mem::swap(&mut channels.0, &mut channels.2);
+        vst4q_u8(chunk.as_mut_ptr(), channels);
+    }
+    bgra_to_rgba_scalar(chunks.into_remainder());
+}
+
+/// The matrix used to turn RGB into luma and chroma
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvMatrix {
+    /// Standard definition video, what most JPEG and webcam pipelines assume
+    Bt601,
+    /// High definition video
+    Bt709,
+}
+
+/// How much of the 8 bit range the samples use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvRange {
+    /// 0-255 for every plane, sometimes called PC or JPEG range
+    Full,
+    /// 16-235 for luma and 16-240 for chroma, sometimes called TV or video range
+    Limited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct YuvColorSpace {
+    pub matrix: YuvMatrix,
+    pub range: YuvRange,
+}
+
+impl YuvColorSpace {
+    pub const BT601_LIMITED: YuvColorSpace =
+        YuvColorSpace::new(YuvMatrix::Bt601, YuvRange::Limited);
+    pub const BT601_FULL: YuvColorSpace = YuvColorSpace::new(YuvMatrix::Bt601, YuvRange::Full);
+    pub const BT709_LIMITED: YuvColorSpace =
+        YuvColorSpace::new(YuvMatrix::Bt709, YuvRange::Limited);
+    pub const BT709_FULL: YuvColorSpace = YuvColorSpace::new(YuvMatrix::Bt709, YuvRange::Full);
+
+    pub const fn new(matrix: YuvMatrix, range: YuvRange) -> Self {
+        YuvColorSpace { matrix, range }
+    }
+
+    /// Red and blue weights, green is the rest
+    fn kr_kb(&self) -> (f32, f32) {
+        match self.matrix {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+
+    /// Luma scale, chroma scale and luma offset
+    fn scale(&self) -> (f32, f32, f32) {
+        match self.range {
+            YuvRange::Full => (1.0, 1.0, 0.0),
+            YuvRange::Limited => (219.0 / 255.0, 224.0 / 255.0, 16.0),
+        }
+    }
+
+    fn coefficients(&self) -> YuvCoefficients {
+        let (kr, kb) = self.kr_kb();
+        let (y_scale, c_scale, y_offset) = self.scale();
+        let fixed = |value: f32| (value * (1 << YUV_SHIFT) as f32).round() as i32;
+        // round the weights so they sum exactly, white stays white and grey has no chroma
+        let (yr, yb) = (fixed(kr * y_scale), fixed(kb * y_scale));
+        let yg = fixed(y_scale) - yr - yb;
+        let (ur, ub) = (
+            fixed(-kr / (2.0 * (1.0 - kb)) * c_scale),
+            fixed(0.5 * c_scale),
+        );
+        let (vr, vb) = (
+            fixed(0.5 * c_scale),
+            fixed(-kb / (2.0 * (1.0 - kr)) * c_scale),
+        );
+        YuvCoefficients {
+            y: [yb, yg, yr],
+            u: [ub, -ur - ub, ur],
+            v: [vb, -vr - vb, vr],
+            y_offset: ((y_offset as i32) << YUV_SHIFT) + (1 << (YUV_SHIFT - 1)),
+            // chroma is computed from the sum of a 2x2 block, so 2 more bits of shift
+            c_offset: (128 << (YUV_SHIFT + 2)) + (1 << (YUV_SHIFT + 1)),
+        }
+    }
+}
+
+const YUV_SHIFT: i32 = 14;
+
+/// Fixed point weights in BGR order, shared by the scalar and SIMD paths so they agree exactly
+#[derive(Debug, Clone, Copy)]
+struct YuvCoefficients {
+    y: [i32; 3],
+    u: [i32; 3],
+    v: [i32; 3],
+    y_offset: i32,
+    c_offset: i32,
+}
+
+impl YuvCoefficients {
+    fn luma(&self, b: i32, g: i32, r: i32) -> u8 {
+        let [cb, cg, cr] = self.y;
+        ((cb * b + cg * g + cr * r + self.y_offset) >> YUV_SHIFT).clamp(0, 255) as u8
+    }
+
+    /// From the sums of a 2x2 block
+    fn chroma(&self, b: i32, g: i32, r: i32) -> (u8, u8) {
+        let apply = |[cb, cg, cr]: [i32; 3]| {
+            ((cb * b + cg * g + cr * r + self.c_offset) >> (YUV_SHIFT + 2)).clamp(0, 255) as u8
+        };
+        (apply(self.u), apply(self.v))
+    }
+}
+
+/// Bytes needed for a 4:2:0 frame, I420 and NV12 are the same size.
+///
+/// Chroma is rounded up for odd sizes.
+pub fn yuv420_len(width: u32, height: u32) -> usize {
+    let (width, height) = (width as usize, height as usize);
+    width * height + 2 * width.div_ceil(2) * height.div_ceil(2)
+}
+
+/// Convert BGRA to planar 4:2:0, a full size Y plane followed by quarter size U and V planes
+pub fn bgra_to_i420(
+    bgra: &[u8],
+    width: u32,
+    height: u32,
+    space: YuvColorSpace,
+    i420: &mut [u8],
+) -> WincResult<()> {
+    bgra_to_yuv420(bgra, width, height, space, i420, false)
+}
+
+/// Convert BGRA to semi planar 4:2:0, a full size Y plane followed by one plane of interleaved U and V
+pub fn bgra_to_nv12(
+    bgra: &[u8],
+    width: u32,
+    height: u32,
+    space: YuvColorSpace,
+    nv12: &mut [u8],
+) -> WincResult<()> {
+    bgra_to_yuv420(bgra, width, height, space, nv12, true)
+}
+
+fn bgra_to_yuv420(
+    bgra: &[u8],
+    width: u32,
+    height: u32,
+    space: YuvColorSpace,
+    yuv: &mut [u8],
+    interleaved: bool,
+) -> WincResult<()> {
+    check_buffer_size(width as usize * height as usize * 4, bgra.len())?;
+    check_buffer_size(yuv420_len(width, height), yuv.len())?;
+    let coefficients = space.coefficients();
+    let (width, height) = (width as usize, height as usize);
+    let (y_plane, chroma) = yuv.split_at_mut(width * height);
+    if width == 0 {
+        return Ok(());
+    }
+
+    for (bgra_row, y_row) in bgra
+        .chunks_exact(width * 4)
+        .zip(y_plane.chunks_exact_mut(width))
+    {
+        luma_row(bgra_row, y_row, &coefficients);
+    }
+
+    let chroma_width = width.div_ceil(2);
+    let plane_len = chroma.len() / 2;
+    for chroma_y in 0..height.div_ceil(2) {
+        let row0 = &bgra[chroma_y * 2 * width * 4..][..width * 4];
+        // the last row of an odd height image pairs with itself
+        let row1 = &bgra[(chroma_y * 2 + 1).min(height - 1) * width * 4..][..width * 4];
+        if interleaved {
+            let uv_row = &mut chroma[chroma_y * chroma_width * 2..][..chroma_width * 2];
+            chroma_row(row0, row1, &coefficients, |x, u, v| {
+                uv_row[x * 2] = u;
+                uv_row[x * 2 + 1] = v;
+            });
+        } else {
+            let (u_plane, v_plane) = chroma.split_at_mut(plane_len);
+            let u_row = &mut u_plane[chroma_y * chroma_width..][..chroma_width];
+            let v_row = &mut v_plane[chroma_y * chroma_width..][..chroma_width];
+            chroma_row(row0, row1, &coefficients, |x, u, v| {
+                u_row[x] = u;
+                v_row[x] = v;
+            });
+        }
+    }
+    Ok(())
+}
+
+fn luma_row(bgra: &[u8], y: &mut [u8], coefficients: &YuvCoefficients) {
+    let done = {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("ssse3") {
+                unsafe { luma_row_ssse3(bgra, y, coefficients) }
+            } else {
+                0
+            }
+        }
+        // NEON is mandatory on aarch64
+        #[cfg(target_arch = "aarch64")]
+        {
+            unsafe { luma_row_neon(bgra, y, coefficients) }
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            0
+        }
+    };
+    luma_row_scalar(&bgra[done * 4..], &mut y[done..], coefficients);
+}
+
+fn luma_row_scalar(bgra: &[u8], y: &mut [u8], coefficients: &YuvCoefficients) {
+    for (pixel, y) in bgra.chunks_exact(4).zip(y.iter_mut()) {
+        *y = coefficients.luma(pixel[0] as i32, pixel[1] as i32, pixel[2] as i32);
+    }
+}
+
+/// Calls `write(x, u, v)` for every chroma sample of a pair of rows
+fn chroma_row(
+    row0: &[u8],
+    row1: &[u8],
+    coefficients: &YuvCoefficients,
+    mut write: impl FnMut(usize, u8, u8),
+) {
+    let done = {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("ssse3") {
+                unsafe { chroma_row_ssse3(row0, row1, coefficients, &mut write) }
+            } else {
+                0
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            unsafe { chroma_row_neon(row0, row1, coefficients, &mut write) }
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            0
+        }
+    };
+    chroma_row_scalar(row0, row1, coefficients, done, write);
+}
+
+fn chroma_row_scalar(
+    row0: &[u8],
+    row1: &[u8],
+    coefficients: &YuvCoefficients,
+    start: usize,
+    mut write: impl FnMut(usize, u8, u8),
+) {
+    let width = row0.len() / 4;
+    for x in start..width.div_ceil(2) {
+        // the last column of an odd width image pairs with itself
+        let left = x * 2 * 4;
+        let right = (x * 2 + 1).min(width - 1) * 4;
+        let sum = |channel: usize| {
+            row0[left + channel] as i32
+                + row0[right + channel] as i32
+                + row1[left + channel] as i32
+                + row1[right + channel] as i32
+        };
+        let (u, v) = coefficients.chroma(sum(0), sum(1), sum(2));
+        write(x, u, v);
+    }
+}
+
+/// Luma for 16 pixels at a time, returning how many pixels were done
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "ssse3")]
+unsafe fn luma_row_ssse3(bgra: &[u8], y: &mut [u8], coefficients: &YuvCoefficients) -> usize {
+    let [cb, cg, cr] = coefficients.y.map(|c| c as i16);
+    let weights = _mm_setr_epi16(cb, cg, cr, 0, cb, cg, cr, 0);
+    let offset = _mm_set1_epi32(coefficients.y_offset);
+    let zero = _mm_setzero_si128();
+    // 4 pixels to 4 i32 lumas, madd sums B+G and R+A of each pixel, hadd sums those
+    let luma4 = |pixels: __m128i| {
+        let lo = _mm_madd_epi16(_mm_unpacklo_epi8(pixels, zero), weights);
+        let hi = _mm_madd_epi16(_mm_unpackhi_epi8(pixels, zero), weights);
+        _mm_srai_epi32::<YUV_SHIFT>(_mm_add_epi32(_mm_hadd_epi32(lo, hi), offset))
+    };
+    let blocks = y.len().min(bgra.len() / 4) / 16;
+    for block in 0..blocks {
+        let src = bgra.as_ptr().add(block * 64) as *const __m128i;
+        let y0 = luma4(_mm_loadu_si128(src));
+        let y1 = luma4(_mm_loadu_si128(src.add(1)));
+        let y2 = luma4(_mm_loadu_si128(src.add(2)));
+        let y3 = luma4(_mm_loadu_si128(src.add(3)));
+        let packed = _mm_packus_epi16(_mm_packs_epi32(y0, y1), _mm_packs_epi32(y2, y3));
+        _mm_storeu_si128(y.as_mut_ptr().add(block * 16) as *mut __m128i, packed);
+    }
+    blocks * 16
+}
+
+/// Chroma for 8 pixels of 2 rows at a time, returning how many chroma samples were done
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "ssse3")]
+unsafe fn chroma_row_ssse3(
+    row0: &[u8],
+    row1: &[u8],
+    coefficients: &YuvCoefficients,
+    write: &mut impl FnMut(usize, u8, u8),
+) -> usize {
+    let [ub, ug, ur] = coefficients.u.map(|c| c as i16);
+    let [vb, vg, vr] = coefficients.v.map(|c| c as i16);
+    let u_weights = _mm_setr_epi16(ub, ug, ur, 0, ub, ug, ur, 0);
+    let v_weights = _mm_setr_epi16(vb, vg, vr, 0, vb, vg, vr, 0);
+    let offset = _mm_set1_epi32(coefficients.c_offset);
+    let zero = _mm_setzero_si128();
+    // bytes 0, 1, 4, 5 of the packed result are U and 2, 3, 6, 7 are V
+    let split = _mm_setr_epi8(0, 1, 4, 5, 2, 3, 6, 7, -1, -1, -1, -1, -1, -1, -1, -1);
+    // 4 pixels of each row to [U01, U23, V01, V23], the sums of each 2x2 block fit in i16
+    let chroma2 = |top: __m128i, bottom: __m128i| {
+        let lo = _mm_add_epi16(
+            _mm_unpacklo_epi8(top, zero),
+            _mm_unpacklo_epi8(bottom, zero),
+        );
+        let hi = _mm_add_epi16(
+            _mm_unpackhi_epi8(top, zero),
+            _mm_unpackhi_epi8(bottom, zero),
+        );
+        let sums = _mm_unpacklo_epi64(
+            _mm_add_epi16(lo, _mm_srli_si128::<8>(lo)),
+            _mm_add_epi16(hi, _mm_srli_si128::<8>(hi)),
+        );
+        let u = _mm_madd_epi16(sums, u_weights);
+        let v = _mm_madd_epi16(sums, v_weights);
+        _mm_srai_epi32::<{ YUV_SHIFT + 2 }>(_mm_add_epi32(_mm_hadd_epi32(u, v), offset))
+    };
+    let blocks = row0.len().min(row1.len()) / 32;
+    for block in 0..blocks {
+        let top = row0.as_ptr().add(block * 32) as *const __m128i;
+        let bottom = row1.as_ptr().add(block * 32) as *const __m128i;
+        let first = chroma2(_mm_loadu_si128(top), _mm_loadu_si128(bottom));
+        let second = chroma2(_mm_loadu_si128(top.add(1)), _mm_loadu_si128(bottom.add(1)));
+        let packed = _mm_packus_epi16(_mm_packs_epi32(first, second), zero);
+        let samples = _mm_shuffle_epi8(packed, split);
+        let u = (_mm_cvtsi128_si32(samples) as u32).to_le_bytes();
+        let v = (_mm_cvtsi128_si32(_mm_srli_si128::<4>(samples)) as u32).to_le_bytes();
+        for i in 0..4 {
+            write(block * 4 + i, u[i], v[i]);
+        }
+    }
+    blocks * 4
+}
+
+/// Luma for 16 pixels at a time, returning how many pixels were done
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn luma_row_neon(bgra: &[u8], y: &mut [u8], coefficients: &YuvCoefficients) -> usize {
+    // luma weights are all positive, so unsigned math gives the same result as the scalar path
+    let [cb, cg, cr] = coefficients.y.map(|c| c as u16);
+    let offset = coefficients.y_offset as u32;
+    let luma4 = |b: uint16x4_t, g: uint16x4_t, r: uint16x4_t| {
+        let acc = vmlal_n_u16(vdupq_n_u32(offset), b, cb);
+        let acc = vmlal_n_u16(acc, g, cg);
+        let acc = vmlal_n_u16(acc, r, cr);
+        vqmovn_u32(vshrq_n_u32::<YUV_SHIFT>(acc))
+    };
+    let blocks = y.len().min(bgra.len() / 4) / 16;
+    for block in 0..blocks {
+        let channels = vld4q_u8(bgra.as_ptr().add(block * 64));
+        let (b_lo, b_hi) = (
+            vmovl_u8(vget_low_u8(channels.0)),
+            vmovl_u8(vget_high_u8(channels.0)),
+        );
+        let (g_lo, g_hi) = (
+            vmovl_u8(vget_low_u8(channels.1)),
+            vmovl_u8(vget_high_u8(channels.1)),
+        );
+        let (r_lo, r_hi) = (
+            vmovl_u8(vget_low_u8(channels.2)),
+            vmovl_u8(vget_high_u8(channels.2)),
+        );
+        let lo = vcombine_u16(
+            luma4(vget_low_u16(b_lo), vget_low_u16(g_lo), vget_low_u16(r_lo)),
+            luma4(
+                vget_high_u16(b_lo),
+                vget_high_u16(g_lo),
+                vget_high_u16(r_lo),
+            ),
+        );
+        let hi = vcombine_u16(
+            luma4(vget_low_u16(b_hi), vget_low_u16(g_hi), vget_low_u16(r_hi)),
+            luma4(
+                vget_high_u16(b_hi),
+                vget_high_u16(g_hi),
+                vget_high_u16(r_hi),
+            ),
+        );
+        vst1q_u8(
+            y.as_mut_ptr().add(block * 16),
+            vcombine_u8(vqmovn_u16(lo), vqmovn_u16(hi)),
+        );
+    }
+    blocks * 16
+}
+
+/// Chroma for 16 pixels of 2 rows at a time, returning how many chroma samples were done
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn chroma_row_neon(
+    row0: &[u8],
+    row1: &[u8],
+    coefficients: &YuvCoefficients,
+    write: &mut impl FnMut(usize, u8, u8),
+) -> usize {
+    let offset = coefficients.c_offset;
+    // the sums of each 2x2 block of one channel, 8 blocks split into 2 halves of 4 for the i32 math
+    let sums = |top: uint8x16_t, bottom: uint8x16_t| {
+        let sums = vaddq_u16(vpaddlq_u8(top), vpaddlq_u8(bottom));
+        (
+            vreinterpretq_s32_u32(vmovl_u16(vget_low_u16(sums))),
+            vreinterpretq_s32_u32(vmovl_u16(vget_high_u16(sums))),
         )
     };
-    // For each 16-byte chunk in your data
-    for chunk in data.chunks_exact_mut(16) {
-        let mut vector = unsafe { _mm_loadu_si128(chunk.as_ptr() as *const __m128i) };
-        vector = unsafe { _mm_shuffle_epi8(vector, mask) };
-        unsafe { _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, vector) };
+    // chroma weights are signed, unlike luma, so this is i32 math like the scalar path
+    let chroma4 = |[cb, cg, cr]: [i32; 3], b: int32x4_t, g: int32x4_t, r: int32x4_t| {
+        let acc = vmlaq_n_s32(vdupq_n_s32(offset), b, cb);
+        let acc = vmlaq_n_s32(acc, g, cg);
+        let acc = vmlaq_n_s32(acc, r, cr);
+        vqmovun_s32(vshrq_n_s32::<{ YUV_SHIFT + 2 }>(acc))
+    };
+    let blocks = row0.len().min(row1.len()) / 64;
+    for block in 0..blocks {
+        let top = vld4q_u8(row0.as_ptr().add(block * 64));
+        let bottom = vld4q_u8(row1.as_ptr().add(block * 64));
+        let (b_lo, b_hi) = sums(top.0, bottom.0);
+        let (g_lo, g_hi) = sums(top.1, bottom.1);
+        let (r_lo, r_hi) = sums(top.2, bottom.2);
+        let channel = |weights: [i32; 3]| {
+            let samples = vcombine_u16(
+                chroma4(weights, b_lo, g_lo, r_lo),
+                chroma4(weights, b_hi, g_hi, r_hi),
+            );
+            let mut bytes = [0u8; 8];
+            vst1_u8(bytes.as_mut_ptr(), vqmovn_u16(samples));
+            bytes
+        };
+        let (u, v) = (channel(coefficients.u), channel(coefficients.v));
+        for i in 0..8 {
+            write(block * 8 + i, u[i], v[i]);
+        }
+    }
+    blocks * 8
+}
+
+/// Convert planar 4:2:0 back to BGRA with opaque alpha, mostly for checking what an encoder will see
+pub fn i420_to_bgra(
+    i420: &[u8],
+    width: u32,
+    height: u32,
+    space: YuvColorSpace,
+    bgra: &mut [u8],
+) -> WincResult<()> {
+    yuv420_to_bgra(i420, width, height, space, bgra, false)
+}
+
+/// Convert semi planar 4:2:0 back to BGRA with opaque alpha
+pub fn nv12_to_bgra(
+    nv12: &[u8],
+    width: u32,
+    height: u32,
+    space: YuvColorSpace,
+    bgra: &mut [u8],
+) -> WincResult<()> {
+    yuv420_to_bgra(nv12, width, height, space, bgra, true)
+}
+
+fn yuv420_to_bgra(
+    yuv: &[u8],
+    width: u32,
+    height: u32,
+    space: YuvColorSpace,
+    bgra: &mut [u8],
+    interleaved: bool,
+) -> WincResult<()> {
+    check_buffer_size(yuv420_len(width, height), yuv.len())?;
+    check_buffer_size(width as usize * height as usize * 4, bgra.len())?;
+    let (kr, kb) = space.kr_kb();
+    let kg = 1.0 - kr - kb;
+    let (y_scale, c_scale, y_offset) = space.scale();
+    let (width, height) = (width as usize, height as usize);
+    let chroma_width = width.div_ceil(2);
+    let chroma_len = chroma_width * height.div_ceil(2);
+    let (y_plane, chroma) = yuv.split_at(width * height);
+    for (i, pixel) in bgra.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % width, i / width);
+        let c = y / 2 * chroma_width + x / 2;
+        let (u, v) = if interleaved {
+            (chroma[c * 2], chroma[c * 2 + 1])
+        } else {
+            (chroma[c], chroma[chroma_len + c])
+        };
+        let luma = (y_plane[i] as f32 - y_offset) / y_scale;
+        let cb = (u as f32 - 128.0) / c_scale;
+        let cr = (v as f32 - 128.0) / c_scale;
+        let r = luma + 2.0 * (1.0 - kr) * cr;
+        let b = luma + 2.0 * (1.0 - kb) * cb;
+        let g = (luma - kr * r - kb * b) / kg;
+        let to_u8 = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+        pixel.copy_from_slice(&[to_u8(b), to_u8(g), to_u8(r), 255]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bgra(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    fn reference(data: &[u8]) -> Vec<u8> {
+        let mut expected = data.to_vec();
+        for (i, byte) in expected.iter_mut().enumerate() {
+            let pixel = i / 4 * 4;
+            if pixel + 4 <= data.len() {
+                *byte = data[pixel + [2, 1, 0, 3][i % 4]];
+            }
+        }
+        expected
+    }
+
+    fn check_all_lengths(convert: impl Fn(&mut [u8])) {
+        // every length up to a few multiples of the widest vector, including partial pixels
+        for len in 0..=64 * 4 + 3 {
+            let input = bgra(len);
+            let mut data = input.clone();
+            convert(&mut data);
+            assert_eq!(data, reference(&input), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn scalar_all_lengths() {
+        check_all_lengths(bgra_to_rgba_scalar);
+    }
+
+    #[test]
+    fn dispatch_all_lengths() {
+        check_all_lengths(bgra_to_rgba);
+    }
+
+    #[test]
+    fn odd_width_region_converts_last_pixels() {
+        // 3x3 region, 9 pixels, the last one used to be left as BGRA
+        let mut data = [10, 20, 30, 255].repeat(9);
+        bgra_to_rgba(&mut data);
+        assert_eq!(data, [30, 20, 10, 255].repeat(9));
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn ssse3_all_lengths() {
+        if is_x86_feature_detected!("ssse3") {
+            check_all_lengths(|data| unsafe { bgra_to_rgba_ssse3(data) });
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn avx2_all_lengths() {
+        if is_x86_feature_detected!("avx2") {
+            check_all_lengths(|data| unsafe { bgra_to_rgba_avx2(data) });
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn neon_all_lengths() {
+        check_all_lengths(|data| unsafe { bgra_to_rgba_neon(data) });
+    }
+
+    const SPACES: [YuvColorSpace; 4] = [
+        YuvColorSpace::BT601_FULL,
+        YuvColorSpace::BT601_LIMITED,
+        YuvColorSpace::BT709_FULL,
+        YuvColorSpace::BT709_LIMITED,
+    ];
+
+    fn scalar_yuv420(bgra: &[u8], width: usize, height: usize, space: YuvColorSpace) -> Vec<u8> {
+        let coefficients = space.coefficients();
+        let chroma_width = width.div_ceil(2);
+        let mut yuv = vec![0; yuv420_len(width as u32, height as u32)];
+        let (y_plane, chroma) = yuv.split_at_mut(width * height);
+        luma_row_scalar(bgra, y_plane, &coefficients);
+        let (u_plane, v_plane) = chroma.split_at_mut(chroma_width * height.div_ceil(2));
+        for chroma_y in 0..height.div_ceil(2) {
+            let row0 = &bgra[chroma_y * 2 * width * 4..][..width * 4];
+            let row1 = &bgra[(chroma_y * 2 + 1).min(height - 1) * width * 4..][..width * 4];
+            chroma_row_scalar(row0, row1, &coefficients, 0, |x, u, v| {
+                u_plane[chroma_y * chroma_width + x] = u;
+                v_plane[chroma_y * chroma_width + x] = v;
+            });
+        }
+        yuv
+    }
+
+    #[test]
+    fn yuv420_len_rounds_chroma_up() {
+        assert_eq!(yuv420_len(4, 2), 8 + 2 * 2);
+        assert_eq!(yuv420_len(3, 3), 9 + 2 * 4);
+        assert_eq!(yuv420_len(0, 0), 0);
+    }
+
+    #[test]
+    fn yuv_simd_matches_scalar() {
+        for space in SPACES {
+            for width in 1..40 {
+                for height in 1..4 {
+                    let input = bgra(width * height * 4);
+                    let mut i420 = vec![0; yuv420_len(width as u32, height as u32)];
+                    bgra_to_i420(&input, width as u32, height as u32, space, &mut i420).unwrap();
+                    assert_eq!(
+                        i420,
+                        scalar_yuv420(&input, width, height, space),
+                        "{:?} {}x{}",
+                        space,
+                        width,
+                        height
+                    );
+
+                    let mut nv12 = vec![0; i420.len()];
+                    bgra_to_nv12(&input, width as u32, height as u32, space, &mut nv12).unwrap();
+                    let (y, chroma) = i420.split_at(width * height);
+                    let (u, v) = chroma.split_at(chroma.len() / 2);
+                    let interleaved: Vec<u8> =
+                        u.iter().zip(v).flat_map(|(&u, &v)| [u, v]).collect();
+                    assert_eq!(nv12, [y, &interleaved].concat());
+                }
+            }
+
+            // pure colors push chroma to the ends of the range, over several SIMD blocks and a scalar tail
+            let (width, height) = (67, 3);
+            let colors = [
+                [255, 0, 0, 255],
+                [0, 0, 255, 255],
+                [0, 255, 0, 0],
+                [255; 4],
+                [0; 4],
+            ];
+            let input: Vec<u8> = (0..width * height)
+                .flat_map(|i| colors[i / 2 % colors.len()])
+                .collect();
+            let mut i420 = vec![0; yuv420_len(width as u32, height as u32)];
+            bgra_to_i420(&input, width as u32, height as u32, space, &mut i420).unwrap();
+            assert_eq!(
+                i420,
+                scalar_yuv420(&input, width, height, space),
+                "{:?}",
+                space
+            );
+        }
+    }
+
+    #[test]
+    fn yuv_matches_float_reference() {
+        for space in SPACES {
+            let (kr, kb) = space.kr_kb();
+            let (y_scale, _, y_offset) = space.scale();
+            let input = bgra(64 * 2 * 4);
+            let mut i420 = vec![0; yuv420_len(64, 2)];
+            bgra_to_i420(&input, 64, 2, space, &mut i420).unwrap();
+            for (i, pixel) in input.chunks_exact(4).enumerate() {
+                let (b, g, r) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+                let luma = kr * r + (1.0 - kr - kb) * g + kb * b;
+                let expected = luma * y_scale + y_offset;
+                assert!(
+                    (i420[i] as f32 - expected).abs() <= 1.0,
+                    "{:?} {}",
+                    space,
+                    i
+                );
+            }
+            // a flat grey block has no chroma, the rounding of the weights cancels out
+            let grey = [90, 90, 90, 255].repeat(4);
+            let mut yuv = vec![0; yuv420_len(2, 2)];
+            bgra_to_i420(&grey, 2, 2, space, &mut yuv).unwrap();
+            let expected = (90.0 * y_scale + y_offset).round() as u8;
+            assert_eq!(yuv, [expected, expected, expected, expected, 128, 128]);
+        }
+    }
+
+    #[test]
+    fn yuv_ranges() {
+        let white = [255u8; 4].repeat(4);
+        let black = [0, 0, 0, 255].repeat(4);
+        let mut yuv = vec![0; yuv420_len(2, 2)];
+        for (space, white_y, black_y) in [
+            (YuvColorSpace::BT709_FULL, 255, 0),
+            (YuvColorSpace::BT709_LIMITED, 235, 16),
+        ] {
+            bgra_to_nv12(&white, 2, 2, space, &mut yuv).unwrap();
+            assert_eq!(yuv, [white_y, white_y, white_y, white_y, 128, 128]);
+            bgra_to_nv12(&black, 2, 2, space, &mut yuv).unwrap();
+            assert_eq!(yuv, [black_y, black_y, black_y, black_y, 128, 128]);
+        }
+        // pure red has the most extreme Cr
+        let red = [0, 0, 255, 255].repeat(4);
+        bgra_to_i420(&red, 2, 2, YuvColorSpace::BT601_LIMITED, &mut yuv).unwrap();
+        assert_eq!(&yuv[4..], &[90, 240]);
+    }
+
+    #[test]
+    fn yuv_round_trip() {
+        // every 2x2 block is one color, so subsampling loses nothing and only rounding remains
+        let (width, height) = (34, 10);
+        let mut input = vec![0u8; width * height * 4];
+        for (i, pixel) in input.chunks_exact_mut(4).enumerate() {
+            let block = (i % width) / 2 + (i / width) / 2 * 17;
+            let color = [block * 37 + 11, block * 91 + 3, block * 53 + 201];
+            pixel.copy_from_slice(&[color[0] as u8, color[1] as u8, color[2] as u8, 255]);
+        }
+        for space in SPACES {
+            let tolerance = match space.range {
+                YuvRange::Full => 2,
+                YuvRange::Limited => 3,
+            };
+            let mut yuv = vec![0; yuv420_len(width as u32, height as u32)];
+            let mut output = vec![0; input.len()];
+            for interleaved in [false, true] {
+                if interleaved {
+                    bgra_to_nv12(&input, width as u32, height as u32, space, &mut yuv).unwrap();
+                    nv12_to_bgra(&yuv, width as u32, height as u32, space, &mut output).unwrap();
+                } else {
+                    bgra_to_i420(&input, width as u32, height as u32, space, &mut yuv).unwrap();
+                    i420_to_bgra(&yuv, width as u32, height as u32, space, &mut output).unwrap();
+                }
+                for (a, b) in input.iter().zip(output.iter()) {
+                    assert!(
+                        (*a as i32 - *b as i32).abs() <= tolerance,
+                        "{:?} {} vs {}",
+                        space,
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn yuv_checks_sizes() {
+        let mut yuv = vec![0; yuv420_len(4, 4) - 1];
+        assert!(bgra_to_i420(&[0; 64], 4, 4, YuvColorSpace::BT709_FULL, &mut yuv).is_err());
+        let mut yuv = vec![0; yuv420_len(4, 4)];
+        assert!(bgra_to_nv12(&[0; 60], 4, 4, YuvColorSpace::BT709_FULL, &mut yuv).is_err());
     }
 }
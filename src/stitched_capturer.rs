@@ -0,0 +1,180 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+
+use crate::prelude::check_buffer_size;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::CaptureSource;
+use crate::prelude::ContainsRect;
+use crate::prelude::HasArea;
+use crate::prelude::HasHeight;
+use crate::prelude::HasTopLeft;
+use crate::prelude::HasWidth;
+use crate::prelude::Intersect;
+use crate::prelude::Metrics;
+use crate::prelude::MonitorInfo;
+use crate::prelude::Rect;
+use crate::prelude::Subtract;
+use crate::prelude::Union;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+
+/// Captures a region spanning several monitors by capturing the piece on each and copying them into one frame
+pub struct StitchedCapturer<S> {
+    pub pieces: Vec<S>,
+    pub capture_region: Rect,
+    /// RGBA color for parts of the region no monitor shows
+    pub fill: [u8; 4],
+    pub alpha_policy: AlphaPolicy,
+    /// The parts of the region not covered by any piece
+    gaps: Vec<Rect>,
+    /// The piece covering most of the region, the primary monitor's piece winning ties, reported as the frame's monitor
+    main_piece: usize,
+    /// Each piece is captured here before being copied into place
+    scratch: RefCell<Vec<u8>>,
+}
+
+/// Stitch `pieces`, each capturing a part of `capture_region` on one monitor, into frames of the whole region.
+///
+/// Frames belong to the monitor whose piece covers most of the region, the primary monitor when several cover as much.
+///
+/// Fails with [`WincError::RegionOutOfBounds`] when there are no pieces or one reaches outside the region.
+pub fn get_stitched_capturer<S: CaptureSource>(
+    pieces: Vec<S>,
+    capture_region: Rect,
+) -> WincResult<StitchedCapturer<S>> {
+    let bounds = pieces.iter().fold(Rect::default(), |bounds, piece| {
+        bounds.union(&piece.region())
+    });
+    if pieces.is_empty() || !capture_region.contains_rect(&bounds) {
+        return Err(WincError::RegionOutOfBounds {
+            region: capture_region,
+            bounds,
+        });
+    }
+    let mut gaps = vec![capture_region];
+    for piece in &pieces {
+        gaps = gaps
+            .iter()
+            .flat_map(|gap| gap.subtract(&piece.region()))
+            .collect();
+    }
+    let main_piece = pieces
+        .iter()
+        .enumerate()
+        .max_by_key(|(i, piece)| {
+            let primary = piece.monitor_info().is_primary;
+            // the first of otherwise equal pieces
+            (piece.region().area(), primary, Reverse(*i))
+        })
+        .map(|(i, _)| i)
+        .unwrap_or_default();
+    Ok(StitchedCapturer {
+        pieces,
+        capture_region,
+        fill: [0, 0, 0, 255],
+        alpha_policy: AlphaPolicy::default(),
+        gaps,
+        main_piece,
+        scratch: RefCell::default(),
+    })
+}
+
+impl<S> StitchedCapturer<S> {
+    /// Change the RGBA color of parts of the region between monitors, opaque black by default
+    pub fn with_fill(mut self, fill: [u8; 4]) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Change what happens to alpha, [`AlphaPolicy::ForceOpaque`] by default.
+    ///
+    /// [`AlphaPolicy::ForceOpaque`] applies to the captured pieces only, so the fill keeps the alpha it was given.
+    pub fn with_alpha_policy(mut self, alpha_policy: AlphaPolicy) -> Self {
+        self.alpha_policy = alpha_policy;
+        self
+    }
+
+    /// The parts of the region that come out as the fill color
+    pub fn gaps(&self) -> &[Rect] {
+        &self.gaps
+    }
+
+    fn force_opaque(&self, bgra: &mut [u8]) {
+        if self.alpha_policy == AlphaPolicy::ForceOpaque {
+            for pixel in bgra.chunks_exact_mut(4) {
+                pixel[3] = 255;
+            }
+        }
+    }
+}
+
+/// Copy a BGRA buffer of `src_rect` into the part of a BGRA buffer of `dst_rect` it covers, both in desktop coordinates
+fn copy_rect(src: &[u8], src_rect: Rect, dst: &mut [u8], dst_rect: Rect) {
+    let row_len = src_rect.width() as usize * 4;
+    let dst_stride = dst_rect.width() as usize * 4;
+    let origin = src_rect.top_left() - dst_rect.top_left();
+    for (row, src_row) in src.chunks_exact(row_len.max(1)).enumerate() {
+        let start = (origin.y as usize + row) * dst_stride + origin.x as usize * 4;
+        dst[start..][..row_len].copy_from_slice(src_row);
+    }
+}
+
+/// Paint the part of `rect` inside a BGRA buffer of `buffer_rect` with an RGBA color, both in desktop coordinates
+pub(crate) fn fill_rect(buffer: &mut [u8], buffer_rect: Rect, rect: Rect, rgba: [u8; 4]) {
+    let Some(rect) = rect.intersection(&buffer_rect) else {
+        return;
+    };
+    let [r, g, b, a] = rgba;
+    let stride = buffer_rect.width() as usize * 4;
+    let origin = rect.top_left() - buffer_rect.top_left();
+    for y in origin.y..origin.y + rect.height() {
+        let start = y as usize * stride + origin.x as usize * 4;
+        for pixel in buffer[start..][..rect.width() as usize * 4].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[b, g, r, a]);
+        }
+    }
+}
+
+impl<S: CaptureSource> CaptureSource for StitchedCapturer<S> {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        let region = self.capture_region;
+        check_buffer_size(region.area() as usize * 4, buffer.len())?;
+        let mut scratch = self.scratch.borrow_mut();
+        for piece in &self.pieces {
+            let piece_region = piece.region();
+            // a piece covering the whole region can write straight into the frame
+            if piece_region == region {
+                piece.capture_bgra_into(buffer, metrics)?;
+                self.force_opaque(buffer);
+                continue;
+            }
+            scratch.resize(piece_region.area() as usize * 4, 0);
+            piece.capture_bgra_into(&mut scratch, metrics)?;
+            self.force_opaque(&mut scratch);
+            metrics.begin("stitch");
+            copy_rect(&scratch, piece_region, buffer, region);
+            metrics.end("stitch");
+        }
+
+        for gap in &self.gaps {
+            fill_rect(buffer, region, *gap, self.fill);
+        }
+        Ok(())
+    }
+
+    fn region(&self) -> Rect {
+        self.capture_region
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        self.pieces[self.main_piece].monitor_info()
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        match self.alpha_policy {
+            // already applied to each piece, forcing it again would make a transparent fill opaque
+            AlphaPolicy::ForceOpaque => AlphaPolicy::Preserve,
+            alpha_policy => alpha_policy,
+        }
+    }
+}
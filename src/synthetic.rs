@@ -0,0 +1,403 @@
+use std::cell::Cell;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+
+use crate::prelude::capture_virtual_screen_from;
+use crate::prelude::check_buffer_size;
+use crate::prelude::get_stitched_capturer;
+use crate::prelude::monitor_id;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::CaptureSource;
+use crate::prelude::Desktop;
+use crate::prelude::Frame;
+use crate::prelude::FrameBuffer;
+use crate::prelude::FrameInfo;
+use crate::prelude::HasHeight;
+use crate::prelude::HasMonitorInfo;
+use crate::prelude::HasTopLeft;
+use crate::prelude::HasWidth;
+use crate::prelude::Intersect;
+use crate::prelude::Metrics;
+use crate::prelude::MonitorInfo;
+use crate::prelude::MonitorSet;
+use crate::prelude::PixelFormat;
+use crate::prelude::Point;
+use crate::prelude::RawImage;
+use crate::prelude::Rect;
+use crate::prelude::Size;
+use crate::prelude::StitchedCapturer;
+use crate::prelude::VirtualScreenOptions;
+use crate::prelude::WincResult;
+use crate::prelude::BASE_DPI;
+use crate::prelude::NATIVE_PIXEL_FORMAT;
+
+/// Deterministic content drawn onto a synthetic monitor.
+///
+/// Patterns are drawn in monitor-local coordinates, the same way a real screen would be,
+/// so captures of a region exercise the same offset math as the GDI capturer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntheticPattern {
+    /// Every pixel is the given RGBA color
+    Solid([u8; 4]),
+    /// Red increases left to right, green increases top to bottom, blue is constant
+    Gradient,
+    /// SMPTE-style color bars
+    SmpteBars,
+    /// A white square on black, moving `speed` pixels diagonally each frame
+    MovingBox { size: i32, speed: i32 },
+    /// The frame number drawn as a row of 32 binary cells along the top edge, most significant bit first
+    FrameCounter { cell_size: NonZeroU32 },
+}
+
+// 75% intensity bars, left to right
+const SMPTE_TOP: [[u8; 3]; 7] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+];
+const SMPTE_MIDDLE: [[u8; 3]; 7] = [
+    [0, 0, 191],
+    [19, 19, 19],
+    [191, 0, 191],
+    [19, 19, 19],
+    [0, 191, 191],
+    [19, 19, 19],
+    [191, 191, 191],
+];
+const SMPTE_BOTTOM: [[u8; 3]; 4] = [[0, 33, 76], [255, 255, 255], [50, 0, 106], [19, 19, 19]];
+
+impl SyntheticPattern {
+    /// The RGBA color of the pixel at monitor-local `at` on a monitor of the given size
+    pub fn pixel(&self, at: Point, monitor_size: Size, frame: u64) -> [u8; 4] {
+        let Point { x, y } = at;
+        let Size { width, height } = monitor_size;
+        match *self {
+            SyntheticPattern::Solid(color) => color,
+            SyntheticPattern::Gradient => {
+                let r = x * 255 / (width - 1).max(1);
+                let g = y * 255 / (height - 1).max(1);
+                [r as u8, g as u8, 128, 255]
+            }
+            SyntheticPattern::SmpteBars => {
+                let [r, g, b] = if y < height * 2 / 3 {
+                    SMPTE_TOP[(x * 7 / width) as usize]
+                } else if y < height * 3 / 4 {
+                    SMPTE_MIDDLE[(x * 7 / width) as usize]
+                } else {
+                    SMPTE_BOTTOM[((x * 4 / width) as usize).min(3)]
+                };
+                [r, g, b, 255]
+            }
+            SyntheticPattern::MovingBox { size, speed } => {
+                let origin = moving_box_origin(size, speed, monitor_size, frame);
+                if x >= origin.x && x < origin.x + size && y >= origin.y && y < origin.y + size {
+                    [255, 255, 255, 255]
+                } else {
+                    [0, 0, 0, 255]
+                }
+            }
+            SyntheticPattern::FrameCounter { cell_size } => {
+                let cell_size = cell_size.get() as i64;
+                let cell = x as i64 / cell_size;
+                if (y as i64) < cell_size && cell < 32 {
+                    let bit = (frame >> (31 - cell)) & 1;
+                    if bit == 1 {
+                        [255, 255, 255, 255]
+                    } else {
+                        [0, 0, 0, 255]
+                    }
+                } else {
+                    [128, 128, 128, 255]
+                }
+            }
+        }
+    }
+}
+
+/// Where the top left of a [`SyntheticPattern::MovingBox`] is on a given frame
+pub fn moving_box_origin(size: i32, speed: i32, monitor_size: Size, frame: u64) -> Point {
+    let travel = frame as i64 * speed as i64;
+    let x = travel.rem_euclid((monitor_size.width - size).max(1) as i64);
+    let y = travel.rem_euclid((monitor_size.height - size).max(1) as i64);
+    Point::new(x as i32, y as i32)
+}
+
+/// Read the frame number back out of a capture of a [`SyntheticPattern::FrameCounter`] monitor
+pub fn decode_frame_counter(image: &RawImage, cell_size: NonZeroU32) -> u64 {
+    let cell_size = cell_size.get();
+    let y = cell_size / 2;
+    (0..32).fold(0, |acc, cell| {
+        let x = cell * cell_size + cell_size / 2;
+        let [r, _, _, _] = image.rgba_at(x, y);
+        (acc << 1) | (r > 127) as u64
+    })
+}
+
+pub struct SyntheticMonitor {
+    pub info: MonitorInfo,
+    pub pattern: SyntheticPattern,
+}
+
+impl HasMonitorInfo for SyntheticMonitor {
+    fn info(&self) -> &MonitorInfo {
+        &self.info
+    }
+}
+
+/// A configurable monitor layout that renders deterministic content, for testing without a display
+#[derive(Default)]
+pub struct SyntheticLayout {
+    pub monitors: Vec<Rc<SyntheticMonitor>>,
+}
+
+impl SyntheticLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a monitor whose work area is the whole monitor
+    pub fn with_monitor(
+        self,
+        name: &str,
+        rect: Rect,
+        is_primary: bool,
+        pattern: SyntheticPattern,
+    ) -> Self {
+        self.with_monitor_work_area(name, rect, rect, is_primary, pattern)
+    }
+
+    pub fn with_monitor_work_area(
+        self,
+        name: &str,
+        rect: Rect,
+        work_area: Rect,
+        is_primary: bool,
+        pattern: SyntheticPattern,
+    ) -> Self {
+        self.with_monitor_info(
+            MonitorInfo {
+                id: monitor_id(name),
+                name: name.to_string(),
+                rect,
+                work_area,
+                is_primary,
+                dpi: BASE_DPI,
+                connector: None,
+                edid: None,
+            },
+            pattern,
+        )
+    }
+
+    /// Add a monitor scaled to `dpi`, [`BASE_DPI`] being 100%. `rect` is in physical pixels like any other.
+    pub fn with_scaled_monitor(
+        self,
+        name: &str,
+        rect: Rect,
+        dpi: u32,
+        is_primary: bool,
+        pattern: SyntheticPattern,
+    ) -> Self {
+        self.with_monitor_info(
+            MonitorInfo {
+                id: monitor_id(name),
+                name: name.to_string(),
+                rect,
+                work_area: rect,
+                is_primary,
+                dpi,
+                connector: None,
+                edid: None,
+            },
+            pattern,
+        )
+    }
+
+    pub fn with_monitor_info(mut self, info: MonitorInfo, pattern: SyntheticPattern) -> Self {
+        self.monitors
+            .push(Rc::new(SyntheticMonitor { info, pattern }));
+        self
+    }
+
+    /// Two 1920x1080 monitors side by side with a 40px taskbar on the primary, like a typical desk
+    pub fn dual_1080p(pattern: SyntheticPattern) -> Self {
+        Self::new()
+            .with_monitor_work_area(
+                r"\\.\DISPLAY1",
+                Rect::new(0, 0, 1920, 1080),
+                Rect::new(0, 0, 1920, 1040),
+                true,
+                pattern,
+            )
+            .with_monitor(
+                r"\\.\DISPLAY2",
+                Rect::new(1920, 0, 3840, 1080),
+                false,
+                pattern,
+            )
+    }
+
+    pub fn monitor_infos(&self) -> Vec<&MonitorInfo> {
+        self.monitors.iter().map(|monitor| &monitor.info).collect()
+    }
+
+    /// Synthetic equivalent of [`get_monitor_infos`](crate::prelude::get_monitor_infos), for [`watch_layout`](crate::prelude::watch_layout) and [`diff_layouts`](crate::prelude::diff_layouts)
+    pub fn snapshot(&self) -> Vec<MonitorInfo> {
+        self.monitors
+            .iter()
+            .map(|monitor| monitor.info.clone())
+            .collect()
+    }
+
+    /// Synthetic equivalent of [`get_multi_monitor_capturer`](crate::prelude::get_multi_monitor_capturer)
+    pub fn multi_monitor_capturer(
+        &self,
+        capture_region: Rect,
+    ) -> WincResult<StitchedCapturer<SyntheticCapturer>> {
+        let pieces = self
+            .monitors
+            .iter()
+            .filter_map(|monitor| {
+                let piece = monitor.info.rect.intersection(&capture_region)?;
+                Some(get_synthetic_capturer(monitor.clone(), piece))
+            })
+            .collect();
+        get_stitched_capturer(pieces, capture_region)
+    }
+
+    /// Synthetic equivalent of [`capture_virtual_screen`](crate::prelude::capture_virtual_screen)
+    pub fn capture_virtual_screen(
+        &self,
+        options: &VirtualScreenOptions,
+        metrics: &mut Metrics,
+    ) -> WincResult<Frame> {
+        let capturers = self
+            .monitors
+            .iter()
+            .map(|monitor| {
+                get_synthetic_capturer(monitor.clone(), options.monitor_region(&monitor.info))
+            })
+            .collect();
+        capture_virtual_screen_from(capturers, options, metrics)
+    }
+
+    /// Synthetic equivalent of [`get_monitor_set`](crate::prelude::get_monitor_set)
+    pub fn monitor_set(&self) -> MonitorSet<Rc<SyntheticMonitor>> {
+        self.monitors.iter().cloned().collect()
+    }
+
+    /// Synthetic equivalent of [`Desktop::current`](crate::prelude::Desktop::current)
+    pub fn desktop(&self) -> Desktop {
+        Desktop::new(self.snapshot())
+    }
+
+    /// Synthetic equivalent of [`get_full_monitor_capturers`](crate::prelude::get_full_monitor_capturers)
+    pub fn full_monitor_capturers(&self) -> Vec<SyntheticCapturer> {
+        self.monitors
+            .iter()
+            .map(|monitor| get_synthetic_capturer(monitor.clone(), monitor.info.rect))
+            .collect()
+    }
+}
+
+pub struct SyntheticCapturer {
+    pub monitor: Rc<SyntheticMonitor>,
+    pub capture_region: Rect,
+    pub alpha_policy: AlphaPolicy,
+    frame: Cell<u64>,
+}
+
+/// Synthetic equivalent of [`get_monitor_capturer`](crate::prelude::get_monitor_capturer)
+pub fn get_synthetic_capturer(
+    monitor: Rc<SyntheticMonitor>,
+    capture_region: Rect,
+) -> SyntheticCapturer {
+    SyntheticCapturer {
+        monitor,
+        capture_region,
+        alpha_policy: AlphaPolicy::default(),
+        frame: Cell::new(0),
+    }
+}
+
+impl SyntheticCapturer {
+    /// Change what happens to alpha, [`AlphaPolicy::ForceOpaque`] by default
+    pub fn with_alpha_policy(mut self, alpha_policy: AlphaPolicy) -> Self {
+        self.alpha_policy = alpha_policy;
+        self
+    }
+
+    /// The number of frames captured so far
+    pub fn frame(&self) -> u64 {
+        self.frame.get()
+    }
+
+    #[cfg(feature = "image")]
+    pub fn capture(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        CaptureSource::capture(self, metrics)
+    }
+
+    pub fn capture_raw(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        self.capture_as(NATIVE_PIXEL_FORMAT, metrics)
+    }
+
+    pub fn capture_as(&self, format: PixelFormat, metrics: &mut Metrics) -> WincResult<Frame> {
+        CaptureSource::capture_as(self, format, metrics)
+    }
+
+    pub fn capture_into(
+        &self,
+        frame: &mut FrameBuffer,
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        CaptureSource::capture_into(self, frame, metrics)
+    }
+
+    pub fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        let width = self.capture_region.width();
+        let height = self.capture_region.height();
+        check_buffer_size((width * height) as usize * 4, buffer.len())?;
+        let monitor_rect = self.monitor.info.rect;
+        let frame = self.frame.get();
+        self.frame.set(frame + 1);
+
+        // render BGRA like a DIB so the conversion path matches the GDI capturer
+        metrics.begin("render");
+        let origin = self.monitor.info.to_local(self.capture_region.top_left());
+        let monitor_size = monitor_rect.size();
+        for (i, pixel) in buffer.chunks_exact_mut(4).enumerate() {
+            let at = origin + Point::new(i as i32 % width, i as i32 / width);
+            // like GDI, anything off the monitor comes back as transparent black
+            if at.x < 0 || at.y < 0 || at.x >= monitor_size.width || at.y >= monitor_size.height {
+                pixel.fill(0);
+                continue;
+            }
+            let [r, g, b, a] = self.monitor.pattern.pixel(at, monitor_size, frame);
+            pixel.copy_from_slice(&[b, g, r, a]);
+        }
+        metrics.end("render");
+        Ok(())
+    }
+}
+
+impl CaptureSource for SyntheticCapturer {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        SyntheticCapturer::capture_bgra_into(self, buffer, metrics)
+    }
+
+    fn region(&self) -> Rect {
+        self.capture_region
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        &self.monitor.info
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        self.alpha_policy
+    }
+}
@@ -1,25 +1,1085 @@
-#[cfg(test)]
-mod tests {
-    use crate::monitor_region_capturer::get_full_monitor_capturers;
-    use crate::monitor_region_capturer::get_monitor_capturer;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::prelude::decode_frame_counter;
+use crate::prelude::get_scaled_capturer;
+use crate::prelude::get_synthetic_capturer;
+use crate::prelude::monitor_id;
+use crate::prelude::moving_box_origin;
+use crate::prelude::parse_edid;
+use crate::prelude::watch_layout;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::CaptureSource;
+use crate::prelude::FrameBuffer;
+use crate::prelude::FrameInfo;
+#[cfg(feature = "bgra_to_rgba")]
+use crate::prelude::FramePool;
+use crate::prelude::FromCorners;
+use crate::prelude::HasArea;
+use crate::prelude::HasTopLeft;
+use crate::prelude::LayoutEvent;
+#[cfg(all(feature = "serde", feature = "metrics"))]
+use crate::prelude::Metric;
+use crate::prelude::Metrics;
+#[cfg(feature = "serde")]
+use crate::prelude::MonitorIdentity;
+use crate::prelude::MonitorInfo;
+#[cfg(feature = "serde")]
+use crate::prelude::MonitorSet;
+use crate::prelude::PixelFormat;
+use crate::prelude::Point;
+use crate::prelude::RawImage;
+use crate::prelude::Rect;
+use crate::prelude::ResizeFilter;
+use crate::prelude::Size;
+use crate::prelude::SyntheticLayout;
+use crate::prelude::SyntheticPattern;
+use crate::prelude::Translatable;
+use crate::prelude::VirtualScreenOptions;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+use crate::prelude::BASE_DPI;
+use crate::prelude::NATIVE_PIXEL_FORMAT;
+#[cfg(feature = "image")]
+use image::GenericImageView;
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+#[cfg(feature = "image")]
+fn assert_no_transparency(images: &Vec<RgbaImage>) {
+    for capture in images {
+        for pixel in capture.enumerate_pixels() {
+            let image::Rgba([_, _, _, a]) = pixel.2;
+            assert_eq!(*a, 255);
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+fn capture_all(sources: &[Box<dyn CaptureSource>]) -> Vec<RgbaImage> {
+    sources
+        .iter()
+        .map(|source| {
+            let frame = source.capture(&mut Metrics::None).unwrap();
+            frame.into_rgba_image().unwrap()
+        })
+        .collect()
+}
+
+const COUNTER_CELL: NonZeroU32 = NonZeroU32::new(8).unwrap();
+
+struct SolidSource {
+    info: MonitorInfo,
+    bgra: [u8; 4],
+    alpha_policy: AlphaPolicy,
+}
+
+impl CaptureSource for SolidSource {
+    fn capture_bgra_into(&self, buffer: &mut [u8], _metrics: &mut Metrics) -> WincResult<()> {
+        for pixel in buffer.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&self.bgra);
+        }
+        Ok(())
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        self.alpha_policy
+    }
+
+    fn region(&self) -> Rect {
+        self.info.rect
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        &self.info
+    }
+}
+
+fn solid_source() -> Box<dyn CaptureSource> {
+    Box::new(solid_source_with([3, 2, 1, 255], AlphaPolicy::default()))
+}
+
+fn solid_source_with(bgra: [u8; 4], alpha_policy: AlphaPolicy) -> SolidSource {
+    let rect = Rect::from_corners((0, 0), (64, 32));
+    SolidSource {
+        info: MonitorInfo {
+            id: 0,
+            name: "fake".to_string(),
+            rect,
+            work_area: rect,
+            is_primary: true,
+            dpi: BASE_DPI,
+            connector: None,
+            edid: None,
+        },
+        bgra,
+        alpha_policy,
+    }
+}
+
+#[test]
+fn alpha_policies() {
+    let mut metrics = Metrics::None;
+    let gdi_like = [3, 2, 1, 0];
+    let forced = solid_source_with(gdi_like, AlphaPolicy::ForceOpaque);
+    let capture = forced.capture_raw(&mut metrics).unwrap();
+    assert_eq!(capture.rgba_at(0, 0), [1, 2, 3, 255]);
+    capture.validate().unwrap();
+    let capture = forced.capture_as(PixelFormat::Bgra8, &mut metrics).unwrap();
+    assert_eq!(&capture.data[..4], &[3, 2, 1, 255]);
+
+    let preserved = solid_source_with(gdi_like, AlphaPolicy::Preserve);
+    let capture = preserved.capture_raw(&mut metrics).unwrap();
+    assert_eq!(capture.rgba_at(0, 0), [1, 2, 3, 0]);
+    assert!(matches!(
+        capture.validate(),
+        Err(WincError::Transparency {
+            transparent: 2048,
+            translucent: 0
+        })
+    ));
+    let translucent = solid_source_with([3, 2, 1, 128], AlphaPolicy::Preserve);
+    assert!(matches!(
+        translucent.capture_raw(&mut metrics).unwrap().validate(),
+        Err(WincError::Transparency {
+            transparent: 0,
+            translucent: 2048
+        })
+    ));
+
+    let mut buffer = vec![0; 64 * 32 * 4];
+    preserved
+        .capture_into_slice(&mut buffer, &mut metrics)
+        .unwrap();
+    assert_eq!(buffer[3], 0);
+    forced
+        .capture_into_slice(&mut buffer, &mut metrics)
+        .unwrap();
+    assert_eq!(buffer[3], 255);
+}
+
+#[test]
+#[cfg(feature = "bgra_to_rgba")]
+fn alpha_policy_drop() {
+    let mut metrics = Metrics::None;
+    let dropped = solid_source_with([3, 2, 1, 0], AlphaPolicy::Drop);
+    let capture = dropped.capture_raw(&mut metrics).unwrap();
+    assert_eq!(capture.format, PixelFormat::Rgb8);
+    assert_eq!(&capture.data[..3], &[1, 2, 3]);
+    assert_eq!(capture.rgba_at(0, 0), [1, 2, 3, 255]);
+    capture.validate().unwrap();
+    let capture = dropped
+        .capture_as(PixelFormat::Bgra8, &mut metrics)
+        .unwrap();
+    assert_eq!(capture.format, PixelFormat::Bgr8);
+    let capture = dropped
+        .capture_as(PixelFormat::Gray8, &mut metrics)
+        .unwrap();
+    assert_eq!(capture.format, PixelFormat::Gray8);
+
+    let mut frame = FrameBuffer::new(64, 32, PixelFormat::Rgba8);
+    dropped.capture_into(&mut frame, &mut metrics).unwrap();
+    assert_eq!(frame.format(), PixelFormat::Rgb8);
+    assert_eq!(frame.as_bytes().len(), 64 * 32 * 3);
+}
+
+#[test]
+#[cfg(not(feature = "bgra_to_rgba"))]
+fn alpha_policy_drop_needs_conversion_feature() {
+    let dropped = solid_source_with([3, 2, 1, 0], AlphaPolicy::Drop);
+    assert!(matches!(
+        dropped.capture_raw(&mut Metrics::None),
+        Err(WincError::Unsupported(_))
+    ));
+}
+
+/// Tags its frames with another monitor, like a source capturing on behalf of a mirrored display
+struct TaggedSource(SolidSource);
+
+impl CaptureSource for TaggedSource {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        self.0.capture_bgra_into(buffer, metrics)
+    }
+
+    fn region(&self) -> Rect {
+        self.0.region()
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        self.0.monitor_info()
+    }
+
+    fn frame_info(&self) -> FrameInfo {
+        FrameInfo::now(42, self.region())
+    }
+}
+
+#[test]
+fn boxed_source_keeps_overrides() {
+    let source: Box<dyn CaptureSource> = Box::new(TaggedSource(solid_source_with(
+        [3, 2, 1, 255],
+        AlphaPolicy::Preserve,
+    )));
+    assert_eq!(source.frame_info().monitor_id, 42);
+    let frame = source.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!(frame.monitor_id(), 42);
+    let mut buffer = vec![0; 64 * 32 * 4];
+    let info = source
+        .capture_into_slice(&mut buffer, &mut Metrics::None)
+        .unwrap();
+    assert_eq!(info.monitor_id, 42);
+}
+
+#[test]
+fn fake_capture_source() {
+    let capture = solid_source().capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!((capture.width, capture.height), (64, 32));
+    assert_eq!(capture.rgba_at(63, 31), [1, 2, 3, 255]);
+    capture.validate().unwrap();
+}
+
+#[test]
+#[cfg(feature = "bgra_to_rgba")]
+fn capture_as_every_format() {
+    let source = solid_source();
+    for format in PixelFormat::ALL {
+        let capture = source.capture_as(format, &mut Metrics::None).unwrap();
+        assert_eq!(capture.format, format);
+        assert_eq!(capture.data.len(), 64 * 32 * format.bytes_per_pixel());
+        let expected = match format {
+            PixelFormat::Gray8 => [2, 2, 2, 255],
+            _ => [1, 2, 3, 255],
+        };
+        assert_eq!(capture.rgba_at(63, 31), expected);
+    }
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::SmpteBars);
+    let capture = layout.full_monitor_capturers()[0]
+        .capture_as(PixelFormat::Bgr8, &mut Metrics::None)
+        .unwrap();
+    assert_eq!(&capture.data[..3], &[191, 191, 191]);
+}
+
+#[test]
+fn capture_into_reuses_buffer() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::FrameCounter {
+        cell_size: COUNTER_CELL,
+    });
+    let capturer = &layout.full_monitor_capturers()[0];
+    let mut frame = FrameBuffer::new(1920, 1080, NATIVE_PIXEL_FORMAT);
+    let ptr = frame.as_bytes().as_ptr();
+    for expected in 0..3 {
+        capturer
+            .capture_into(&mut frame, &mut Metrics::None)
+            .unwrap();
+        assert_eq!(frame.as_bytes().as_ptr(), ptr);
+        assert_eq!(
+            decode_frame_counter(&frame.to_raw_image(), COUNTER_CELL),
+            expected
+        );
+    }
+
+    let mut buffer = vec![0u8; 1920 * 1080 * 4];
+    capturer
+        .capture_into_slice(&mut buffer, &mut Metrics::None)
+        .unwrap();
+    let raw = RawImage::new(1920, 1080, NATIVE_PIXEL_FORMAT, buffer).unwrap();
+    assert_eq!(decode_frame_counter(&raw, COUNTER_CELL), 3);
+}
+
+#[test]
+fn capture_into_checks_size() {
+    let source = solid_source();
+    // as many bytes as the region, but transposed
+    let mut frame = FrameBuffer::new(32, 64, NATIVE_PIXEL_FORMAT);
+    let error = source
+        .capture_into(&mut frame, &mut Metrics::None)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        WincError::DimensionMismatch { expected, actual }
+            if expected == Size::new(64, 32) && actual == Size::new(32, 64)
+    ));
+    assert_eq!(error.to_string(), "expected a 64x32 frame but got 32x64");
+    frame.resize(64, 32);
+    source.capture_into(&mut frame, &mut Metrics::None).unwrap();
+    assert_eq!(frame.to_raw_image().rgba_at(0, 0), [1, 2, 3, 255]);
+
+    let mut buffer = vec![0u8; 64 * 32 * 4 - 1];
+    assert!(matches!(
+        source.capture_into_slice(&mut buffer, &mut Metrics::None),
+        Err(WincError::SizeMismatch {
+            expected: 8192,
+            actual: 8191
+        })
+    ));
+}
+
+#[test]
+#[cfg(feature = "bgra_to_rgba")]
+fn pooled_capture_into() {
+    let source = solid_source();
+    let pool = FramePool::new(2);
+    for _ in 0..4 {
+        let mut frame = pool.get(64, 32, PixelFormat::Rgb8);
+        source.capture_into(&mut frame, &mut Metrics::None).unwrap();
+        assert_eq!(&frame.as_bytes()[..3], &[1, 2, 3]);
+        assert_eq!(frame.as_bytes().len(), 64 * 32 * 3);
+    }
+    assert_eq!(pool.free_count(), 1);
+}
+
+#[test]
+#[cfg(not(feature = "bgra_to_rgba"))]
+fn capture_as_needs_conversion_feature() {
+    let source = solid_source();
+    assert!(source
+        .capture_as(PixelFormat::Bgra8, &mut Metrics::None)
+        .is_ok());
+    assert!(matches!(
+        source.capture_as(PixelFormat::Rgb8, &mut Metrics::None),
+        Err(WincError::Unsupported(_))
+    ));
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn fake_capture_source_image() {
+    let images = capture_all(&[solid_source()]);
+    assert_eq!(images[0].dimensions(), (64, 32));
+    assert_eq!(images[0].get_pixel(0, 0).0, [1, 2, 3, 255]);
+    assert_no_transparency(&images);
+}
+
+#[test]
+fn monitor_ids() {
+    // ids must not change between releases, people persist them
+    #[cfg(feature = "id")]
+    assert_eq!(
+        monitor_id(r"\\.\DISPLAY1"),
+        fxhash::hash32(r"\\.\DISPLAY1".as_bytes())
+    );
+    #[cfg(not(feature = "id"))]
+    assert_eq!(monitor_id("a"), 0xe40c292c);
+    assert_ne!(monitor_id(r"\\.\DISPLAY1"), monitor_id(r"\\.\DISPLAY2"));
+}
+
+#[test]
+fn synthetic_full_screenshots() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::SmpteBars);
+    let capturers = layout.full_monitor_capturers();
+    assert_eq!(capturers.len(), 2);
+
+    for capturer in capturers.iter() {
+        let capture = capturer.capture_raw(&mut Metrics::None).unwrap();
+        assert_eq!((capture.width, capture.height), (1920, 1080));
+        assert_eq!(capture.format, NATIVE_PIXEL_FORMAT);
+        // first bar is 75% white, last is blue
+        assert_eq!(capture.rgba_at(0, 0), [191, 191, 191, 255]);
+        assert_eq!(capture.rgba_at(1919, 0), [0, 0, 191, 255]);
+        capture.validate().unwrap();
+    }
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn synthetic_image_matches_raw() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Gradient);
+    let monitor = layout.monitors[1].clone();
+    let region = Rect::from_corners((2000, 100), (2064, 164));
+    let capturer = get_synthetic_capturer(monitor, region);
+    let raw = capturer.capture_raw(&mut Metrics::None).unwrap();
+    let image = capturer
+        .capture(&mut Metrics::None)
+        .unwrap()
+        .into_rgba_image()
+        .unwrap();
+    assert_eq!(image.dimensions(), (raw.width, raw.height));
+    for (x, y, pixel) in image.enumerate_pixels() {
+        assert_eq!(pixel.0, raw.rgba_at(x, y));
+    }
+}
+
+#[test]
+fn frames_carry_metadata() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Gradient);
+    let monitor = layout.monitors[1].clone();
+    let region = Rect::from_corners((2000, 100), (2030, 120));
+    let capturer = get_synthetic_capturer(monitor.clone(), region);
+    let first = capturer.capture_raw(&mut Metrics::None).unwrap();
+    let second = capturer
+        .capture_as(PixelFormat::Bgra8, &mut Metrics::None)
+        .unwrap();
+    assert_eq!(first.monitor_id(), monitor.info.id);
+    assert_eq!(first.region(), region);
+    assert_eq!(first.stride(), 30 * 4);
+    assert_eq!(first.format(), NATIVE_PIXEL_FORMAT);
+    assert!(second.sequence() > first.sequence());
+    assert!(second.timestamp() >= first.timestamp());
+
+    let mut frame = FrameBuffer::new(30, 20, PixelFormat::Bgra8);
+    let info = capturer
+        .capture_into(&mut frame, &mut Metrics::None)
+        .unwrap();
+    assert!(info.sequence > second.sequence());
+    assert_eq!(info.region, region);
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn frame_into_dynamic_image() {
+    let source = solid_source();
+    for format in PixelFormat::ALL {
+        let frame = source.capture_as(format, &mut Metrics::None).unwrap();
+        let expected = frame.rgba_at(5, 5);
+        let image = frame.into_dynamic_image().unwrap();
+        assert_eq!(image.dimensions(), (64, 32), "{:?}", format);
+        assert_eq!(image.get_pixel(5, 5).0, expected, "{:?}", format);
+    }
+}
+
+#[test]
+fn scaled_capture() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::SmpteBars);
+    for filter in [
+        ResizeFilter::Nearest,
+        ResizeFilter::Bilinear,
+        ResizeFilter::Area,
+        ResizeFilter::Lanczos3,
+    ] {
+        let source = layout.full_monitor_capturers().remove(1);
+        let region = source.region();
+        let scaled = get_scaled_capturer(source, Size::new(1280, 720), filter);
+        let frame = scaled.capture_raw(&mut Metrics::None).unwrap();
+        assert_eq!((frame.width, frame.height), (1280, 720));
+        assert_eq!(frame.region(), region);
+        // the middle of the first and last bars are far from any edge
+        assert_eq!(frame.rgba_at(90, 100), [191, 191, 191, 255], "{:?}", filter);
+        assert_eq!(frame.rgba_at(1190, 100), [0, 0, 191, 255], "{:?}", filter);
+        frame.validate().unwrap();
+    }
+
+    let scaled = get_scaled_capturer(solid_source(), Size::new(16, 8), ResizeFilter::Area);
+    let mut frame = FrameBuffer::new(16, 8, NATIVE_PIXEL_FORMAT);
+    scaled.capture_into(&mut frame, &mut Metrics::None).unwrap();
+    assert_eq!(frame.to_raw_image().rgba_at(15, 7), [1, 2, 3, 255]);
+}
+
+#[test]
+fn synthetic_region_offsets() {
+    let layout = SyntheticLayout::new().with_monitor(
+        "left",
+        Rect::from_corners((-256, 0), (0, 256)),
+        false,
+        SyntheticPattern::Gradient,
+    );
+    let monitor = layout.monitors[0].clone();
+    let p0 = monitor.info.rect.top_left().translate(64, 32);
+    let region = Rect::from_corners(p0, p0.translate(100, 100));
+    let capturer = get_synthetic_capturer(monitor, region);
+
+    let capture = capturer.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!((capture.width, capture.height), (100, 100));
+    // gradient is 255/255 per pixel on a 256px monitor, so colors are local coordinates
+    assert_eq!(capture.rgba_at(0, 0), [64, 32, 128, 255]);
+    assert_eq!(capture.rgba_at(99, 99), [163, 131, 128, 255]);
+}
+
+#[test]
+fn synthetic_region_off_monitor_is_transparent() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Solid([10, 20, 30, 255]));
+    let monitor = layout.monitors[0].clone();
+    let region = Rect::from_corners((1900, 1060), (1940, 1100));
+    let capturer = get_synthetic_capturer(monitor, region);
+    let capture = capturer.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!(capture.rgba_at(0, 0), [10, 20, 30, 255]);
+    assert_eq!(capture.rgba_at(39, 39), [0, 0, 0, 255]);
+    let capture = capturer
+        .with_alpha_policy(AlphaPolicy::Preserve)
+        .capture_raw(&mut Metrics::None)
+        .unwrap();
+    assert_eq!(capture.rgba_at(39, 39), [0, 0, 0, 0]);
+    // everything but the 20x20 corner still on the monitor
+    assert!(matches!(
+        capture.validate(),
+        Err(WincError::Transparency {
+            transparent: 1200,
+            translucent: 0
+        })
+    ));
+}
+
+#[test]
+fn multi_monitor_capture_is_stitched() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Gradient);
+    let region = Rect::from_corners((1800, 500), (2100, 600));
+    let capturer = layout.multi_monitor_capturer(region).unwrap();
+    assert!(capturer.gaps().is_empty());
+    let frame = capturer.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!((frame.width, frame.height), (300, 100));
+    assert_eq!(frame.region(), region);
+    frame.validate().unwrap();
+    // each side matches what that monitor captures on its own
+    let monitors = &layout.monitors;
+    let left = get_synthetic_capturer(
+        monitors[0].clone(),
+        Rect::from_corners((1800, 500), (1920, 600)),
+    )
+    .capture_raw(&mut Metrics::None)
+    .unwrap();
+    let right = get_synthetic_capturer(
+        monitors[1].clone(),
+        Rect::from_corners((1920, 500), (2100, 600)),
+    )
+    .capture_raw(&mut Metrics::None)
+    .unwrap();
+    for y in 0..100 {
+        assert_eq!(frame.rgba_at(0, y), left.rgba_at(0, y));
+        assert_eq!(frame.rgba_at(119, y), left.rgba_at(119, y));
+        assert_eq!(frame.rgba_at(120, y), right.rgba_at(0, y));
+        assert_eq!(frame.rgba_at(299, y), right.rgba_at(179, y));
+    }
+}
+
+#[test]
+fn multi_monitor_frame_belongs_to_the_biggest_piece() {
+    // a big side monitor next to a smaller primary
+    let layout = SyntheticLayout::new()
+        .with_monitor(
+            "side",
+            Rect::from_corners((-300, 0), (0, 200)),
+            false,
+            SyntheticPattern::Gradient,
+        )
+        .with_monitor(
+            "primary",
+            Rect::from_corners((0, 0), (100, 100)),
+            true,
+            SyntheticPattern::Gradient,
+        )
+        .with_monitor(
+            "right",
+            Rect::from_corners((100, 0), (200, 100)),
+            false,
+            SyntheticPattern::Gradient,
+        )
+        .with_monitor(
+            "far",
+            Rect::from_corners((200, 0), (400, 100)),
+            false,
+            SyntheticPattern::Gradient,
+        );
+    let capturer = layout
+        .multi_monitor_capturer(Rect::from_corners((-300, 0), (10, 200)))
+        .unwrap();
+    assert_eq!(capturer.monitor_info().name, "side");
+    let frame = capturer.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!(frame.monitor_id(), monitor_id("side"));
+
+    // the primary breaks ties
+    let capturer = layout
+        .multi_monitor_capturer(Rect::from_corners((50, 0), (150, 100)))
+        .unwrap();
+    assert_eq!(capturer.monitor_info().name, "primary");
+
+    // without the primary in the region the biggest piece is used
+    let capturer = layout
+        .multi_monitor_capturer(Rect::from_corners((190, 10), (400, 20)))
+        .unwrap();
+    assert_eq!(capturer.monitor_info().name, "far");
+    // and the first of equal pieces
+    let capturer = layout
+        .multi_monitor_capturer(Rect::from_corners((150, 10), (250, 20)))
+        .unwrap();
+    assert_eq!(capturer.monitor_info().name, "right");
+}
+
+#[test]
+fn multi_monitor_gaps_are_filled() {
+    // a monitor to the right of the primary but lower, leaving gaps above and below
+    let layout = SyntheticLayout::new()
+        .with_monitor(
+            "primary",
+            Rect::from_corners((0, 0), (100, 100)),
+            true,
+            SyntheticPattern::Solid([10, 20, 30, 255]),
+        )
+        .with_monitor(
+            "offset",
+            Rect::from_corners((150, 60), (250, 160)),
+            false,
+            SyntheticPattern::Solid([40, 50, 60, 255]),
+        );
+    let region = Rect::from_corners((50, 25), (200, 125));
+    let capturer = layout
+        .multi_monitor_capturer(region)
+        .unwrap()
+        .with_fill([255, 0, 255, 255]);
+    let covered: i64 = capturer.gaps().iter().map(|gap| gap.area()).sum();
+    assert_eq!(covered, 150 * 100 - 50 * 75 - 50 * 65);
+    let frame = capturer.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!(frame.monitor_id(), monitor_id("primary"));
+    assert_eq!(frame.rgba_at(0, 0), [10, 20, 30, 255]);
+    assert_eq!(frame.rgba_at(49, 74), [10, 20, 30, 255]);
+    assert_eq!(frame.rgba_at(50, 0), [255, 0, 255, 255]);
+    assert_eq!(frame.rgba_at(99, 99), [255, 0, 255, 255]);
+    assert_eq!(frame.rgba_at(0, 75), [255, 0, 255, 255]);
+    assert_eq!(frame.rgba_at(100, 34), [255, 0, 255, 255]);
+    assert_eq!(frame.rgba_at(100, 35), [40, 50, 60, 255]);
+    assert_eq!(frame.rgba_at(149, 99), [40, 50, 60, 255]);
+
+    assert!(matches!(
+        layout.multi_monitor_capturer(Rect::from_corners((300, 0), (400, 100))),
+        Err(WincError::RegionOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn transparent_fill_survives_force_opaque() {
+    // GDI style alpha 0 on screen, next to a gap
+    let layout = SyntheticLayout::new()
+        .with_monitor(
+            "primary",
+            Rect::from_corners((0, 0), (100, 100)),
+            true,
+            SyntheticPattern::Solid([10, 20, 30, 0]),
+        )
+        .with_monitor(
+            "offset",
+            Rect::from_corners((100, 50), (200, 150)),
+            false,
+            SyntheticPattern::Solid([40, 50, 60, 0]),
+        );
+    let capturer = layout
+        .multi_monitor_capturer(Rect::from_corners((0, 0), (200, 150)))
+        .unwrap()
+        .with_fill([0, 0, 0, 0]);
+    let frame = capturer.capture_raw(&mut Metrics::None).unwrap();
+    assert_eq!(frame.rgba_at(0, 0), [10, 20, 30, 255]);
+    assert_eq!(frame.rgba_at(150, 100), [40, 50, 60, 255]);
+    assert_eq!(frame.rgba_at(150, 0), [0, 0, 0, 0]);
+
+    let preserved = capturer
+        .with_alpha_policy(AlphaPolicy::Preserve)
+        .capture_raw(&mut Metrics::None)
+        .unwrap();
+    assert_eq!(preserved.rgba_at(0, 0), [10, 20, 30, 0]);
+
+    let options = VirtualScreenOptions::default().with_fill([1, 2, 3, 0]);
+    let frame = layout
+        .capture_virtual_screen(&options, &mut Metrics::None)
+        .unwrap();
+    assert_eq!(frame.rgba_at(0, 0), [10, 20, 30, 255]);
+    assert_eq!(frame.rgba_at(0, 120), [1, 2, 3, 0]);
+}
+
+#[test]
+fn virtual_screen_follows_layout() {
+    let layout = SyntheticLayout::new()
+        .with_monitor_work_area(
+            "primary",
+            Rect::from_corners((0, 0), (100, 100)),
+            Rect::from_corners((0, 0), (100, 90)),
+            true,
+            SyntheticPattern::Solid([10, 20, 30, 255]),
+        )
+        .with_monitor(
+            "left",
+            Rect::from_corners((-60, -20), (0, 40)),
+            false,
+            SyntheticPattern::Solid([40, 50, 60, 255]),
+        );
+    let mut metrics = Metrics::None;
+    let options = VirtualScreenOptions::default().with_fill([1, 2, 3, 255]);
+    let frame = layout
+        .capture_virtual_screen(&options, &mut metrics)
+        .unwrap();
+    assert_eq!(frame.region(), Rect::from_corners((-60, -20), (100, 100)));
+    assert_eq!(frame.format(), NATIVE_PIXEL_FORMAT);
+    assert_eq!(frame.monitor_id(), monitor_id("primary"));
+    // canvas coordinates are desktop coordinates offset by the top left of the layout
+    assert_eq!(frame.rgba_at(0, 0), [40, 50, 60, 255]);
+    assert_eq!(frame.rgba_at(59, 59), [40, 50, 60, 255]);
+    assert_eq!(frame.rgba_at(59, 60), [1, 2, 3, 255]);
+    assert_eq!(frame.rgba_at(60, 0), [1, 2, 3, 255]);
+    assert_eq!(frame.rgba_at(60, 20), [10, 20, 30, 255]);
+    assert_eq!(frame.rgba_at(159, 119), [10, 20, 30, 255]);
+
+    let options = options
+        .with_work_areas_only(true)
+        .with_primary_outline([255, 0, 0, 255]);
+    let frame = layout
+        .capture_virtual_screen(&options, &mut metrics)
+        .unwrap();
+    assert_eq!(frame.region(), Rect::from_corners((-60, -20), (100, 100)));
+    frame.validate().unwrap();
+    assert_eq!(frame.rgba_at(0, 0), [40, 50, 60, 255]);
+    // the outline is inside the primary's edges, drawn over the taskbar that is left out
+    assert_eq!(frame.rgba_at(60, 20), [255, 0, 0, 255]);
+    assert_eq!(frame.rgba_at(63, 50), [255, 0, 0, 255]);
+    assert_eq!(frame.rgba_at(64, 24), [10, 20, 30, 255]);
+    assert_eq!(frame.rgba_at(100, 111), [1, 2, 3, 255]);
+    assert_eq!(frame.rgba_at(100, 116), [255, 0, 0, 255]);
+    assert_eq!(frame.rgba_at(159, 100), [255, 0, 0, 255]);
+}
+
+#[test]
+fn virtual_screen_belongs_to_the_primary() {
+    // the side monitor is enumerated first and has more pixels than the primary
+    let layout = SyntheticLayout::new()
+        .with_monitor(
+            "side",
+            Rect::from_corners((-300, 0), (0, 200)),
+            false,
+            SyntheticPattern::Solid([40, 50, 60, 255]),
+        )
+        .with_monitor(
+            "primary",
+            Rect::from_corners((0, 0), (100, 100)),
+            true,
+            SyntheticPattern::Solid([10, 20, 30, 255]),
+        );
+    let options = VirtualScreenOptions::default().with_primary_outline([255, 0, 0, 255]);
+    let frame = layout
+        .capture_virtual_screen(&options, &mut Metrics::None)
+        .unwrap();
+    assert_eq!(frame.monitor_id(), monitor_id("primary"));
+    assert_eq!(frame.region(), Rect::from_corners((-300, 0), (100, 200)));
+    assert_eq!(frame.rgba_at(300, 0), [255, 0, 0, 255]);
+    assert_eq!(frame.rgba_at(0, 0), [40, 50, 60, 255]);
+}
+
+#[test]
+fn virtual_screen_outline_fits_the_primary() {
+    let layout = SyntheticLayout::new().with_monitor(
+        "primary",
+        Rect::from_corners((0, 0), (100, 60)),
+        true,
+        SyntheticPattern::Solid([10, 20, 30, 255]),
+    );
+    let options = VirtualScreenOptions::default()
+        .with_primary_outline([255, 0, 0, 255])
+        .with_outline_width(1);
+    let frame = layout
+        .capture_virtual_screen(&options, &mut Metrics::None)
+        .unwrap();
+    assert_eq!(frame.rgba_at(0, 30), [255, 0, 0, 255]);
+    assert_eq!(frame.rgba_at(1, 30), [10, 20, 30, 255]);
+
+    // half the height covers the whole monitor
+    let frame = layout
+        .capture_virtual_screen(&options.with_outline_width(30), &mut Metrics::None)
+        .unwrap();
+    assert_eq!(frame.rgba_at(50, 30), [255, 0, 0, 255]);
+    for width in [31, -1] {
+        let result =
+            layout.capture_virtual_screen(&options.with_outline_width(width), &mut Metrics::None);
+        assert!(matches!(
+            result,
+            Err(WincError::InvalidOutlineWidth { width: w, monitor }) if w == width && monitor == Size::new(100, 60)
+        ));
+    }
+    // without an outline the width is unused
+    let options = VirtualScreenOptions::default().with_outline_width(31);
+    assert!(layout
+        .capture_virtual_screen(&options, &mut Metrics::None)
+        .is_ok());
+}
+
+#[test]
+fn monitor_info_names_the_monitor_from_its_edid() {
+    let rect = Rect::from_corners((0, 0), (1920, 1200));
+    let info = MonitorInfo {
+        id: monitor_id(r"\\.\DISPLAY1"),
+        name: r"\\.\DISPLAY1".to_string(),
+        rect,
+        work_area: rect,
+        is_primary: true,
+        dpi: BASE_DPI,
+        connector: None,
+        edid: Some(parse_edid(include_bytes!("../fixtures/edid/dell_u2415.bin")).unwrap()),
+    };
+    assert_eq!(info.make(), Some("Dell"));
+    assert_eq!(info.model().as_deref(), Some("DELL U2415"));
+    assert_eq!(info.serial().as_deref(), Some("7MT0151T0CEL"));
+
+    let unknown = MonitorInfo { edid: None, ..info };
+    assert_eq!(
+        (unknown.make(), unknown.model(), unknown.serial()),
+        (None, None, None)
+    );
+}
+
+#[test]
+fn layout_watcher_reports_hotplug() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Gradient);
+    let monitors = Arc::new(Mutex::new(layout.snapshot()));
+    let snapshot = {
+        let monitors = monitors.clone();
+        move || Ok(monitors.lock().unwrap().clone())
+    };
+    let (watcher, events) = watch_layout(snapshot, Duration::from_millis(5)).unwrap();
+    assert!(events.recv_timeout(Duration::from_millis(50)).is_err());
+
+    // unplug the second monitor and plug a taller one in left of the primary
+    let replugged = SyntheticLayout::new()
+        .with_monitor_info(layout.monitors[0].info.clone(), SyntheticPattern::Gradient)
+        .with_monitor(
+            r"\\.\DISPLAY3",
+            Rect::new(-1440, 0, 0, 2560),
+            false,
+            SyntheticPattern::Gradient,
+        );
+    *monitors.lock().unwrap() = replugged.snapshot();
+    let timeout = Duration::from_secs(5);
+    assert_eq!(
+        events.recv_timeout(timeout).unwrap(),
+        LayoutEvent::Removed(layout.monitors[1].info.clone())
+    );
+    assert_eq!(
+        events.recv_timeout(timeout).unwrap(),
+        LayoutEvent::Added(replugged.monitors[1].info.clone())
+    );
+    assert!(events.recv_timeout(Duration::from_millis(50)).is_err());
+    watcher.stop();
+    // the sender went away with the thread
+    assert!(events.recv().is_err());
+}
+
+#[test]
+fn monitor_set_finds_synthetic_monitors() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Gradient);
+    let monitors = layout.monitor_set();
+    assert_eq!(monitors.len(), 2);
+    let primary = monitors.primary().unwrap();
+    assert_eq!(primary.info.name, r"\\.\DISPLAY1");
+    let off_screen = monitors.nearest((5000, -300)).unwrap();
+    assert_eq!(off_screen.info.name, r"\\.\DISPLAY2");
+    let frame = get_synthetic_capturer(off_screen.clone(), off_screen.info.rect)
+        .capture_raw(&mut Metrics::None)
+        .unwrap();
+    assert_eq!(frame.region(), Rect::new(1920, 0, 3840, 1080));
+}
+
+#[cfg(feature = "serde")]
+fn round_trip<T>(value: &T)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(
+        &serde_json::from_str::<T>(&json).unwrap(),
+        value,
+        "{}",
+        json
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_field_names_are_stable() {
+    let rect = Rect::new(-1920, 0, 0, 1080);
+    assert_eq!(
+        serde_json::to_string(&rect).unwrap(),
+        r#"{"left":-1920,"top":0,"right":0,"bottom":1080}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Point::new(3, -4)).unwrap(),
+        r#"{"x":3,"y":-4}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Size::new(1920, 1080)).unwrap(),
+        r#"{"width":1920,"height":1080}"#
+    );
+
+    let info = &SyntheticLayout::dual_1080p(SyntheticPattern::Gradient).monitors[0].info;
+    let json = serde_json::to_value(info).unwrap();
+    let keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(
+        keys,
+        [
+            "connector",
+            "dpi",
+            "edid",
+            "id",
+            "is_primary",
+            "name",
+            "rect",
+            "work_area"
+        ]
+    );
+    // layouts saved before monitors had a connector or EDID still load
+    let saved = r#"{"id":1,"name":"left","rect":{"left":0,"top":0,"right":10,"bottom":10},
+        "work_area":{"left":0,"top":0,"right":10,"bottom":8},"is_primary":true,"dpi":96}"#;
+    let loaded: MonitorInfo = serde_json::from_str(saved).unwrap();
+    assert_eq!((loaded.connector, loaded.edid), (None, None));
+    assert_eq!(loaded.work_area, Rect::new(0, 0, 10, 8));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_round_trips() {
+    let layout = SyntheticLayout::dual_1080p(SyntheticPattern::Gradient);
+    let mut info = layout.monitors[1].info.clone();
+    info.connector = Some("DP-2".to_string());
+    info.edid = Some(parse_edid(include_bytes!("../fixtures/edid/lg_ultrafine_4k.bin")).unwrap());
+    round_trip(&info);
+    round_trip(&layout.desktop());
+    round_trip(&layout.desktop().split_rect(Rect::new(1800, 0, 2000, 100)));
+    round_trip(&MonitorSet::new(vec![info.clone()]));
+    round_trip(&MonitorIdentity::of(&info));
+    round_trip(&LayoutEvent::Moved {
+        monitor: info.clone(),
+        from: Rect::new(0, 0, 1920, 1080),
+    });
+    round_trip(
+        &VirtualScreenOptions::default()
+            .with_work_areas_only(true)
+            .with_primary_outline([255, 0, 0, 255])
+            .with_format(PixelFormat::Rgb8),
+    );
+    round_trip(&AlphaPolicy::Drop);
+}
+
+#[test]
+#[cfg(all(feature = "serde", feature = "metrics"))]
+fn metrics_serialize_completed_timings() {
+    let completed = vec![
+        Metric {
+            name: "capture".to_string(),
+            duration: Duration::from_micros(1500),
+        },
+        Metric {
+            name: "convert".to_string(),
+            duration: Duration::from_micros(250),
+        },
+    ];
+    let metrics = Metrics::from_completed(completed.clone());
+    let json = serde_json::to_string(&metrics).unwrap();
+    let loaded: Metrics = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        json,
+        r#"[{"name":"capture","duration":{"secs":0,"nanos":1500000}},{"name":"convert","duration":{"secs":0,"nanos":250000}}]"#
+    );
+    assert_eq!(loaded.completed(), completed);
+    assert_eq!(loaded.total(), Duration::from_micros(1750));
+}
+
+#[test]
+fn logical_regions_capture_physical_pixels() {
+    let layout = SyntheticLayout::new()
+        .with_scaled_monitor(
+            "hidpi",
+            Rect::from_corners((0, 0), (300, 200)),
+            144,
+            true,
+            SyntheticPattern::Gradient,
+        )
+        .with_monitor(
+            "normal",
+            Rect::from_corners((300, 0), (400, 100)),
+            false,
+            SyntheticPattern::Gradient,
+        );
+    let hidpi = &layout.monitors[0].info;
+    assert_eq!(hidpi.scale_factor(), 1.5);
+    assert_eq!(hidpi.logical_size(), Size::new(200, 133));
+
+    let logical = Rect::from_corners((10, 10), (110, 60));
+    let physical = hidpi.logical_to_physical(logical);
+    assert_eq!(physical, Rect::from_corners((15, 15), (165, 90)));
+    assert_eq!(hidpi.physical_to_logical(physical), logical);
+    assert_eq!(hidpi.logical_point_to_physical((3, 3)), Point::new(5, 5));
+    assert_eq!(hidpi.physical_point_to_logical((5, 5)), Point::new(3, 3));
+    let frame = get_synthetic_capturer(layout.monitors[0].clone(), physical)
+        .capture_raw(&mut Metrics::None)
+        .unwrap();
+    assert_eq!((frame.width, frame.height), (150, 75));
+    // the gradient is x * 255 / 299 and y * 255 / 199 in physical pixels
+    assert_eq!(frame.rgba_at(0, 0), [12, 19, 128, 255]);
+
+    // scaling is anchored at each monitor's top left, so 100% is the identity even off the origin
+    let normal = &layout.monitors[1].info;
+    let rect = Rect::from_corners((310, 20), (350, 40));
+    assert_eq!(normal.logical_to_physical(rect), rect);
+    let scaled = SyntheticLayout::new().with_scaled_monitor(
+        "offset",
+        Rect::from_corners((-300, 0), (0, 200)),
+        192,
+        false,
+        SyntheticPattern::Gradient,
+    );
+    assert_eq!(
+        scaled.monitors[0]
+            .info
+            .logical_to_physical(Rect::from_corners((-300, 0), (-250, 50))),
+        Rect::from_corners((-300, 0), (-200, 100))
+    );
+}
+
+#[test]
+fn synthetic_moving_box() {
+    let pattern = SyntheticPattern::MovingBox { size: 16, speed: 5 };
+    let layout = SyntheticLayout::new().with_monitor(
+        "box",
+        Rect::from_corners((0, 0), (128, 64)),
+        true,
+        pattern,
+    );
+    let capturer = &layout.full_monitor_capturers()[0];
+    for frame in 0..20 {
+        let capture = capturer.capture_raw(&mut Metrics::None).unwrap();
+        let origin = moving_box_origin(16, 5, Size::new(128, 64), frame);
+        let (x, y) = (origin.x as u32, origin.y as u32);
+        assert_eq!(capture.rgba_at(x, y), [255, 255, 255, 255]);
+        assert_eq!(capture.rgba_at(x + 15, y + 15), [255, 255, 255, 255]);
+    }
+}
+
+#[test]
+fn synthetic_frame_counter() {
+    let layout = SyntheticLayout::new().with_monitor(
+        "counter",
+        Rect::from_corners((0, 0), (256, 16)),
+        true,
+        SyntheticPattern::FrameCounter {
+            cell_size: COUNTER_CELL,
+        },
+    );
+    let capturer = &layout.full_monitor_capturers()[0];
+    for frame in 0..10 {
+        let capture = capturer.capture_raw(&mut Metrics::None).unwrap();
+        assert_eq!(decode_frame_counter(&capture, COUNTER_CELL), frame);
+    }
+    assert_eq!(capturer.frame(), 10);
+}
+
+#[cfg(all(windows, feature = "image"))]
+mod gdi {
+    use crate::prelude::capture_virtual_screen;
     use crate::prelude::get_all_monitors;
+    use crate::prelude::get_full_monitor_capture_sources;
+    use crate::prelude::get_full_monitor_capturers;
+    use crate::prelude::get_monitor_capturer;
     use crate::prelude::get_monitor_infos;
+    use crate::prelude::get_monitor_set;
+    use crate::prelude::get_multi_monitor_capturer;
+    use crate::prelude::CaptureSource;
+    use crate::prelude::Frame;
+    use crate::prelude::FrameBuffer;
     use crate::prelude::FromCorners;
     use crate::prelude::HasTopLeft;
     use crate::prelude::Metrics;
+    use crate::prelude::Rect;
     use crate::prelude::Translatable;
+    use crate::prelude::Union;
+    use crate::prelude::VirtualScreenOptions;
+    use crate::prelude::NATIVE_PIXEL_FORMAT;
     use std::rc::Rc;
-    use image::RgbaImage;
-    use windows::Win32::Foundation::RECT;
-
-    fn assert_no_transparency(images: &Vec<RgbaImage>) {
-        for capture in images {
-            for pixel in capture.enumerate_pixels() {
-                let image::Rgba([_, _, _, a]) = pixel.2;
-                assert_eq!(*a, 255);
-            }
-        }
-    }
+
+    use super::assert_no_transparency;
+    use super::capture_all;
 
     #[test]
     fn names() {
@@ -28,6 +1088,14 @@ mod tests {
         });
     }
 
+    #[test]
+    fn primary_monitor() {
+        let monitors = get_monitor_set().unwrap();
+        let primary = monitors.primary().unwrap();
+        assert_eq!(primary.info.rect.top_left(), (0, 0).into());
+        assert!(std::ptr::eq(monitors.at_point((0, 0)).unwrap(), primary));
+    }
+
     #[test]
     fn full_screenshots() {
         let capturers = get_full_monitor_capturers().unwrap();
@@ -35,7 +1103,11 @@ mod tests {
 
         let mut images = Vec::new();
         for capturer in capturers.iter() {
-            let capture = capturer.capture(&mut Metrics::None).unwrap();
+            let capture = capturer
+                .capture(&mut Metrics::None)
+                .unwrap()
+                .into_rgba_image()
+                .unwrap();
             let mon_name_good = capturer.monitor.info.name.replace(r"\\.\", "");
             let path = format!("target/capture/full-{}.png", mon_name_good);
             capture.save(path).unwrap();
@@ -45,6 +1117,27 @@ mod tests {
         assert_no_transparency(&images);
     }
 
+    #[test]
+    fn full_screenshots_into_buffer() {
+        let capturers = get_full_monitor_capturers().unwrap();
+        for capturer in capturers.iter() {
+            let size = capturer.capture_region.size();
+            let mut frame =
+                FrameBuffer::new(size.width as u32, size.height as u32, NATIVE_PIXEL_FORMAT);
+            let ptr = frame.as_bytes().as_ptr();
+            for _ in 0..3 {
+                capturer
+                    .capture_into(&mut frame, &mut Metrics::None)
+                    .unwrap();
+                assert_eq!(frame.as_bytes().as_ptr(), ptr);
+            }
+            let info = capturer
+                .capture_into(&mut frame, &mut Metrics::None)
+                .unwrap();
+            Frame::new(info, frame.into_raw_image()).validate().unwrap();
+        }
+    }
+
     #[test]
     fn full_screenshots_with_metrics() {
         let capturers = get_full_monitor_capturers().unwrap();
@@ -53,7 +1146,11 @@ mod tests {
         let mut images = Vec::new();
         for capturer in capturers.iter() {
             let mut metrics = Metrics::new();
-            let capture = capturer.capture(&mut metrics).unwrap();
+            let capture = capturer
+                .capture(&mut metrics)
+                .unwrap()
+                .into_rgba_image()
+                .unwrap();
             println!(
                 "Metrics ({}): {}",
                 capturer.monitor.info.name,
@@ -65,10 +1162,58 @@ mod tests {
             capture.save(path).unwrap();
             images.push(capture);
         }
-        
+
         assert_no_transparency(&images);
     }
 
+    #[test]
+    fn full_screenshots_dyn() {
+        let sources = get_full_monitor_capture_sources().unwrap();
+        let images = capture_all(&sources);
+        for (source, image) in sources.iter().zip(images.iter()) {
+            let size = source.size();
+            assert_eq!(image.dimensions(), (size.width as u32, size.height as u32));
+        }
+        assert_no_transparency(&images);
+    }
+
+    #[test]
+    fn virtual_screen_stitched() {
+        let monitors: Vec<_> = get_all_monitors()
+            .unwrap()
+            .into_iter()
+            .map(Rc::new)
+            .collect();
+        let bounds = monitors.iter().fold(Rect::default(), |bounds, monitor| {
+            bounds.union(&monitor.info.rect)
+        });
+        let capturer = get_multi_monitor_capturer(&monitors, bounds).unwrap();
+        let frame = capturer.capture_raw(&mut Metrics::None).unwrap();
+        assert_eq!(frame.region(), bounds);
+        frame.validate().unwrap();
+        std::fs::create_dir_all("target/capture").unwrap();
+        frame
+            .into_rgba_image()
+            .unwrap()
+            .save("target/capture/virtual-screen.png")
+            .unwrap();
+    }
+
+    #[test]
+    fn virtual_screen() {
+        let options = VirtualScreenOptions::default()
+            .with_work_areas_only(true)
+            .with_primary_outline([255, 0, 0, 255]);
+        let frame = capture_virtual_screen(&options, &mut Metrics::None).unwrap();
+        frame.validate().unwrap();
+        std::fs::create_dir_all("target/capture").unwrap();
+        frame
+            .into_rgba_image()
+            .unwrap()
+            .save("target/capture/virtual-screen-work-areas.png")
+            .unwrap();
+    }
+
     #[test]
     fn region_screenshots() {
         let monitors = get_all_monitors().unwrap();
@@ -77,7 +1222,7 @@ mod tests {
         for monitor in monitors {
             let p0 = monitor.info.rect.top_left();
             let p1 = p0.translate(100, 100);
-            let region = RECT::from_corners(p0, p1).translate(100, 100);
+            let region = Rect::from_corners(p0, p1).translate(100, 100);
             let capturer = get_monitor_capturer(Rc::new(monitor), region);
             capturers.push(capturer);
         }
@@ -86,7 +1231,11 @@ mod tests {
         let mut images = Vec::new();
         for capturer in capturers.iter() {
             // capture
-            let capture = capturer.capture(&mut Metrics::None).unwrap();
+            let capture = capturer
+                .capture(&mut Metrics::None)
+                .unwrap()
+                .into_rgba_image()
+                .unwrap();
 
             // save image
             let mon_name_good = capturer.monitor.info.name.replace(r"\\.\", "");
@@ -105,7 +1254,11 @@ mod tests {
 
         for _ in 0..100 {
             capturers.iter().for_each(|capturer| {
-                let capture = capturer.capture(&mut Metrics::None).unwrap();
+                let capture = capturer
+                    .capture(&mut Metrics::None)
+                    .unwrap()
+                    .into_rgba_image()
+                    .unwrap();
                 let (mut tot_r, mut tot_g, mut tot_b) = (0, 0, 0);
 
                 for pixel in capture.enumerate_pixels() {
@@ -143,3 +1296,141 @@ mod tests {
         assert!(fps > 10);
     }
 }
+
+#[cfg(all(unix, feature = "x11", feature = "image"))]
+mod x11 {
+    use crate::prelude::connect_x11;
+    use crate::prelude::get_all_x11_monitors;
+    use crate::prelude::get_full_x11_monitor_capturers;
+    use crate::prelude::get_x11_monitor_capturer;
+    use crate::prelude::FromCorners;
+    use crate::prelude::HasBottomRight;
+    use crate::prelude::HasTopLeft;
+    use crate::prelude::Metrics;
+    use crate::prelude::Rect;
+    use crate::prelude::Translatable;
+    use crate::prelude::WincError;
+    use crate::prelude::X11Connection;
+    use std::rc::Rc;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt as _;
+    use x11rb::protocol::xproto::CreateGCAux;
+    use x11rb::protocol::xproto::Rectangle;
+
+    // run with `xvfb-run -s "-screen 0 1280x720x24" cargo test --features x11`
+    fn connect() -> Option<Rc<X11Connection>> {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAY not set, skipping X11 test");
+            return None;
+        }
+        Some(connect_x11(None).unwrap())
+    }
+
+    fn fill(connection: &X11Connection, rect: Rect, rgb: u32) {
+        let conn = &connection.connection;
+        let gc = conn.generate_id().unwrap();
+        conn.create_gc(gc, connection.root, &CreateGCAux::new().foreground(rgb))
+            .unwrap();
+        conn.poly_fill_rectangle(
+            connection.root,
+            gc,
+            &[Rectangle {
+                x: rect.left as i16,
+                y: rect.top as i16,
+                width: (rect.right - rect.left) as u16,
+                height: (rect.bottom - rect.top) as u16,
+            }],
+        )
+        .unwrap();
+        conn.free_gc(gc).unwrap();
+        // round trip so the drawing is done before we capture
+        conn.get_input_focus().unwrap().reply().unwrap();
+    }
+
+    #[test]
+    fn x11_full_screenshots() {
+        let Some(connection) = connect() else { return };
+        let capturers = get_full_x11_monitor_capturers(connection).unwrap();
+        assert!(!capturers.is_empty());
+        for capturer in capturers.iter() {
+            let capture = capturer
+                .capture(&mut Metrics::None)
+                .unwrap()
+                .into_rgba_image()
+                .unwrap();
+            let rect = capturer.monitor.info.rect;
+            assert_eq!(
+                capture.dimensions(),
+                (
+                    (rect.right - rect.left) as u32,
+                    (rect.bottom - rect.top) as u32
+                )
+            );
+            assert!(capture.pixels().all(|pixel| pixel.0[3] == 255));
+        }
+    }
+
+    #[test]
+    fn x11_region_colors() {
+        let Some(connection) = connect() else { return };
+        let monitor = get_all_x11_monitors(connection.clone())
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+        let p0 = monitor.info.rect.top_left().translate(50, 50);
+        let region = Rect::from_corners(p0, p0.translate(30, 20));
+        fill(
+            &connection,
+            Rect::from_corners(p0, p0.translate(15, 20)),
+            0xff0000,
+        );
+        fill(
+            &connection,
+            Rect::from_corners(p0.translate(15, 0), p0.translate(30, 20)),
+            0x0080ff,
+        );
+
+        let monitor = Rc::new(monitor);
+        let shm = get_x11_monitor_capturer(monitor.clone(), region);
+        let get_image = get_x11_monitor_capturer(monitor, region).without_shm();
+        assert!(!get_image.uses_shm());
+        for capturer in [shm, get_image] {
+            let capture = capturer
+                .capture(&mut Metrics::None)
+                .unwrap()
+                .into_rgba_image()
+                .unwrap();
+            assert_eq!(capture.dimensions(), (30, 20));
+            assert_eq!(capture.get_pixel(0, 0).0, [255, 0, 0, 255]);
+            assert_eq!(capture.get_pixel(14, 19).0, [255, 0, 0, 255]);
+            assert_eq!(capture.get_pixel(15, 0).0, [0, 128, 255, 255]);
+            assert_eq!(capture.get_pixel(29, 19).0, [0, 128, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn x11_region_out_of_bounds() {
+        let Some(connection) = connect() else { return };
+        let monitor = get_all_x11_monitors(connection.clone())
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+        let bounds = connection.bounds;
+        let region = Rect::from_corners(
+            bounds.bottom_right().translate(-10, -10),
+            bounds.bottom_right().translate(10, 10),
+        );
+        let capturer = get_x11_monitor_capturer(Rc::new(monitor), region);
+        match capturer.capture(&mut Metrics::None) {
+            Err(WincError::RegionOutOfBounds {
+                region: r,
+                bounds: b,
+            }) => {
+                assert_eq!((r, b), (region, bounds));
+            }
+            other => panic!("expected RegionOutOfBounds, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::prelude::fill_rect;
+use crate::prelude::get_stitched_capturer;
+use crate::prelude::CaptureSource;
+use crate::prelude::Frame;
+use crate::prelude::FrameInfo;
+use crate::prelude::HasArea;
+use crate::prelude::Inflate;
+use crate::prelude::Metrics;
+use crate::prelude::MonitorInfo;
+use crate::prelude::PixelFormat;
+use crate::prelude::RawImage;
+use crate::prelude::Rect;
+use crate::prelude::Subtract;
+use crate::prelude::Union;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+use crate::prelude::NATIVE_PIXEL_FORMAT;
+
+/// How [`capture_virtual_screen`](crate::prelude::capture_virtual_screen) draws the desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VirtualScreenOptions {
+    /// Capture each monitor's work area, leaving taskbars as fill
+    pub work_areas_only: bool,
+    /// RGBA color of an outline drawn just inside the primary monitor's edges
+    pub primary_outline: Option<[u8; 4]>,
+    /// Width of the primary outline in pixels, 4 by default
+    pub outline_width: i32,
+    /// RGBA color of parts of the canvas no monitor shows
+    pub fill: [u8; 4],
+    pub format: PixelFormat,
+}
+
+impl Default for VirtualScreenOptions {
+    fn default() -> Self {
+        VirtualScreenOptions {
+            work_areas_only: false,
+            primary_outline: None,
+            outline_width: 4,
+            fill: [0, 0, 0, 255],
+            format: NATIVE_PIXEL_FORMAT,
+        }
+    }
+}
+
+impl VirtualScreenOptions {
+    pub fn with_work_areas_only(mut self, work_areas_only: bool) -> Self {
+        self.work_areas_only = work_areas_only;
+        self
+    }
+
+    pub fn with_primary_outline(mut self, rgba: [u8; 4]) -> Self {
+        self.primary_outline = Some(rgba);
+        self
+    }
+
+    /// Change the width of the primary outline, at most half the primary monitor's width and height
+    pub fn with_outline_width(mut self, width: i32) -> Self {
+        self.outline_width = width;
+        self
+    }
+
+    pub fn with_fill(mut self, rgba: [u8; 4]) -> Self {
+        self.fill = rgba;
+        self
+    }
+
+    pub fn with_format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The part of a monitor to capture
+    pub fn monitor_region(&self, info: &MonitorInfo) -> Rect {
+        if self.work_areas_only {
+            info.work_area
+        } else {
+            info.rect
+        }
+    }
+}
+
+/// Composite captures of every monitor at its position on a canvas covering all of them.
+///
+/// Each of `capturers` captures [`VirtualScreenOptions::monitor_region`] of its monitor.
+/// The frame belongs to the primary monitor, or to the first capturer's monitor when none is primary.
+///
+/// Fails with [`WincError::InvalidOutlineWidth`] when the primary outline does not fit inside the primary monitor.
+pub fn capture_virtual_screen_from<S: CaptureSource>(
+    capturers: Vec<S>,
+    options: &VirtualScreenOptions,
+    metrics: &mut Metrics,
+) -> WincResult<Frame> {
+    // the canvas covers whole monitors even when only work areas are captured
+    let bounds = capturers.iter().fold(Rect::default(), |bounds, capturer| {
+        bounds.union(&capturer.monitor_info().rect)
+    });
+    let stitched = get_stitched_capturer(capturers, bounds)?.with_fill(options.fill);
+    let main = stitched
+        .pieces
+        .iter()
+        .map(|capturer| capturer.monitor_info())
+        .find(|info| info.is_primary)
+        .unwrap_or_else(|| stitched.pieces[0].monitor_info());
+    let primary = main.is_primary.then_some(main.rect);
+    if let (Some(_), Some(primary)) = (options.primary_outline, primary) {
+        let size = primary.size();
+        let width = options.outline_width;
+        // a wider outline would inflate the inside of the monitor into an inverted rect
+        if width < 0 || width > size.width.min(size.height) / 2 {
+            return Err(WincError::InvalidOutlineWidth {
+                width,
+                monitor: size,
+            });
+        }
+    }
+
+    let mut data = vec![0u8; bounds.area() as usize * 4];
+    // the stitched capturer would pick the biggest monitor
+    let info = FrameInfo::now(main.id, bounds);
+    stitched.capture_bgra_into(&mut data, metrics)?;
+    if let (Some(rgba), Some(primary)) = (options.primary_outline, primary) {
+        let inside = primary.inflate(-options.outline_width, -options.outline_width);
+        for edge in primary.subtract(&inside) {
+            fill_rect(&mut data, bounds, edge, rgba);
+        }
+    }
+    let size = bounds.size();
+    let image = RawImage::from_bgra_with_alpha(
+        size.width as u32,
+        size.height as u32,
+        data,
+        options.format,
+        stitched.alpha_policy(),
+        metrics,
+    )?;
+    Ok(Frame::new(info, image))
+}
@@ -0,0 +1,516 @@
+use std::ptr;
+use std::rc::Rc;
+use x11rb::connection::Connection;
+use x11rb::connection::RequestConnection;
+use x11rb::errors::ConnectError;
+use x11rb::errors::ConnectionError;
+use x11rb::errors::ReplyError;
+use x11rb::errors::ReplyOrIdError;
+use x11rb::protocol::randr;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::shm;
+use x11rb::protocol::shm::ConnectionExt as _;
+use x11rb::protocol::xproto::AtomEnum;
+use x11rb::protocol::xproto::ConnectionExt as _;
+use x11rb::protocol::xproto::ImageFormat;
+use x11rb::protocol::xproto::ImageOrder;
+use x11rb::protocol::xproto::Window;
+use x11rb::rust_connection::RustConnection;
+
+use crate::prelude::capture_virtual_screen_from;
+use crate::prelude::check_buffer_size;
+use crate::prelude::get_stitched_capturer;
+use crate::prelude::monitor_id;
+use crate::prelude::parse_edid;
+use crate::prelude::AlphaPolicy;
+use crate::prelude::CaptureSource;
+use crate::prelude::Edid;
+use crate::prelude::Frame;
+use crate::prelude::FrameBuffer;
+use crate::prelude::FrameInfo;
+use crate::prelude::HasHeight;
+use crate::prelude::HasLeft;
+use crate::prelude::HasMonitorInfo;
+use crate::prelude::HasTop;
+use crate::prelude::HasWidth;
+use crate::prelude::Intersect;
+use crate::prelude::Metrics;
+use crate::prelude::MonitorInfo;
+use crate::prelude::MonitorSet;
+use crate::prelude::PixelFormat;
+use crate::prelude::Rect;
+use crate::prelude::StitchedCapturer;
+use crate::prelude::VirtualScreenOptions;
+use crate::prelude::WincError;
+use crate::prelude::WincResult;
+use crate::prelude::BASE_DPI;
+use crate::prelude::NATIVE_PIXEL_FORMAT;
+
+// x11rb errors are only ever surfaced as backend errors
+impl From<ConnectError> for WincError {
+    fn from(e: ConnectError) -> Self {
+        WincError::Backend(Box::new(e))
+    }
+}
+impl From<ConnectionError> for WincError {
+    fn from(e: ConnectionError) -> Self {
+        WincError::Backend(Box::new(e))
+    }
+}
+impl From<ReplyError> for WincError {
+    fn from(e: ReplyError) -> Self {
+        WincError::Backend(Box::new(e))
+    }
+}
+impl From<ReplyOrIdError> for WincError {
+    fn from(e: ReplyOrIdError) -> Self {
+        WincError::Backend(Box::new(e))
+    }
+}
+
+/// A connection to an X server, shared by every monitor and capturer created from it
+pub struct X11Connection {
+    pub connection: RustConnection,
+    pub root: Window,
+    /// The size of the root window, which is everything that can be captured
+    pub bounds: Rect,
+    has_shm: bool,
+}
+
+/// Connect to the display named by `$DISPLAY`, or `display_name` if given
+pub fn connect_x11(display_name: Option<&str>) -> WincResult<Rc<X11Connection>> {
+    let (connection, screen_num) = x11rb::connect(display_name)?;
+    let setup = connection.setup();
+    let screen = &setup.roots[screen_num];
+
+    // we only understand the common little endian 32 bits per pixel 0xRRGGBB layout, which is BGRX in memory
+    let bits_per_pixel = setup
+        .pixmap_formats
+        .iter()
+        .find(|format| format.depth == screen.root_depth)
+        .map(|format| format.bits_per_pixel);
+    let visual = screen
+        .allowed_depths
+        .iter()
+        .flat_map(|depth| depth.visuals.iter())
+        .find(|visual| visual.visual_id == screen.root_visual);
+    let supported = setup.image_byte_order == ImageOrder::LSB_FIRST
+        && bits_per_pixel == Some(32)
+        && visual.is_some_and(|visual| {
+            visual.red_mask == 0xff0000 && visual.green_mask == 0xff00 && visual.blue_mask == 0xff
+        });
+    if !supported {
+        return Err(WincError::Unsupported(format!(
+            "root depth {} with {:?} bits per pixel is not 32-bit BGRX",
+            screen.root_depth, bits_per_pixel
+        )));
+    }
+
+    let root = screen.root;
+    let bounds = Rect::new(
+        0,
+        0,
+        screen.width_in_pixels as i32,
+        screen.height_in_pixels as i32,
+    );
+    let has_shm = connection
+        .extension_information(shm::X11_EXTENSION_NAME)?
+        .is_some();
+    Ok(Rc::new(X11Connection {
+        connection,
+        root,
+        bounds,
+        has_shm,
+    }))
+}
+
+pub struct X11Monitor {
+    pub info: MonitorInfo,
+    pub connection: Rc<X11Connection>,
+}
+
+/// Enumerate monitors using RandR, or treat the whole root window as one monitor if RandR 1.5 is unavailable
+pub fn get_x11_monitor_infos(connection: &X11Connection) -> WincResult<Vec<MonitorInfo>> {
+    let conn = &connection.connection;
+    let has_randr = conn
+        .extension_information(randr::X11_EXTENSION_NAME)?
+        .is_some();
+    let randr_version = if has_randr {
+        let version = conn.randr_query_version(1, 5)?.reply()?;
+        (version.major_version, version.minor_version)
+    } else {
+        (0, 0)
+    };
+
+    if randr_version < (1, 5) {
+        let geometry = conn.get_geometry(connection.root)?.reply()?;
+        let rect = Rect {
+            left: 0,
+            top: 0,
+            right: geometry.width as i32,
+            bottom: geometry.height as i32,
+        };
+        let name = "screen".to_string();
+        return Ok(vec![MonitorInfo {
+            id: monitor_id(&name),
+            name,
+            rect,
+            work_area: rect,
+            is_primary: true,
+            dpi: BASE_DPI,
+            connector: None,
+            edid: None,
+        }]);
+    }
+
+    let monitors = conn
+        .randr_get_monitors(connection.root, true)?
+        .reply()?
+        .monitors;
+    // drivers expose the EDID as an output property, virtual outputs have none
+    let edid_atom = conn.intern_atom(true, b"EDID")?.reply()?.atom;
+    let mut results = Vec::with_capacity(monitors.len());
+    for monitor in monitors {
+        let name = conn.get_atom_name(monitor.name)?.reply()?.name;
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let rect = Rect {
+            left: monitor.x as i32,
+            top: monitor.y as i32,
+            right: monitor.x as i32 + monitor.width as i32,
+            bottom: monitor.y as i32 + monitor.height as i32,
+        };
+        results.push(MonitorInfo {
+            id: monitor_id(&name),
+            name,
+            rect,
+            work_area: rect, // X has no per-monitor work area
+            is_primary: monitor.primary,
+            // X has no per-monitor scale, toolkits read Xft.dpi for the whole screen
+            dpi: BASE_DPI,
+            connector: get_x11_monitor_connector(connection, &monitor.outputs)?,
+            edid: get_x11_monitor_edid(connection, edid_atom, &monitor.outputs)?,
+        });
+    }
+    Ok(results)
+}
+
+/// The name of the first of a monitor's outputs, like `DP-1`, which the driver keeps across reboots
+fn get_x11_monitor_connector(
+    connection: &X11Connection,
+    outputs: &[randr::Output],
+) -> WincResult<Option<String>> {
+    let Some(&output) = outputs.first() else {
+        return Ok(None);
+    };
+    let info = connection
+        .connection
+        .randr_get_output_info(output, x11rb::CURRENT_TIME)?
+        .reply()?;
+    Ok(Some(String::from_utf8_lossy(&info.name).into_owned()))
+}
+
+/// The EDID of the first of a monitor's outputs that has a valid one
+fn get_x11_monitor_edid(
+    connection: &X11Connection,
+    edid_atom: u32,
+    outputs: &[randr::Output],
+) -> WincResult<Option<Edid>> {
+    if edid_atom == u32::from(AtomEnum::NONE) {
+        return Ok(None);
+    }
+    for &output in outputs {
+        // 256 longs covers the base block and up to seven extensions
+        let property = connection
+            .connection
+            .randr_get_output_property(output, edid_atom, AtomEnum::ANY, 0, 256, false, false)?
+            .reply()?;
+        if let Ok(edid) = parse_edid(&property.data) {
+            return Ok(Some(edid));
+        }
+    }
+    Ok(None)
+}
+
+impl HasMonitorInfo for X11Monitor {
+    fn info(&self) -> &MonitorInfo {
+        &self.info
+    }
+}
+
+/// Same as [`get_all_x11_monitors`], but with lookups like [`MonitorSet::primary`]
+pub fn get_x11_monitor_set(connection: Rc<X11Connection>) -> WincResult<MonitorSet<X11Monitor>> {
+    Ok(MonitorSet::new(get_all_x11_monitors(connection)?))
+}
+
+pub fn get_all_x11_monitors(connection: Rc<X11Connection>) -> WincResult<Vec<X11Monitor>> {
+    Ok(get_x11_monitor_infos(&connection)?
+        .into_iter()
+        .map(|info| X11Monitor {
+            info,
+            connection: connection.clone(),
+        })
+        .collect())
+}
+
+/// A System V shared memory segment attached to both us and the X server
+struct ShmSegment {
+    seg: shm::Seg,
+    addr: *mut u8,
+    size: usize,
+}
+
+impl ShmSegment {
+    fn new(connection: &X11Connection, size: usize) -> WincResult<ShmSegment> {
+        let shmid = unsafe { libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600) };
+        if shmid == -1 {
+            return Err(WincError::Backend(
+                Box::new(std::io::Error::last_os_error()),
+            ));
+        }
+        let addr = unsafe { libc::shmat(shmid, ptr::null(), 0) };
+        let attach = if addr as isize == -1 {
+            Err(WincError::Backend(
+                Box::new(std::io::Error::last_os_error()),
+            ))
+        } else {
+            Self::attach(connection, shmid)
+        };
+        // mark for deletion now so the segment is freed once both sides detach, even if we crash
+        unsafe { libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut()) };
+
+        match attach {
+            Ok(seg) => Ok(ShmSegment {
+                seg,
+                addr: addr as *mut u8,
+                size,
+            }),
+            Err(e) => {
+                if addr as isize != -1 {
+                    unsafe { libc::shmdt(addr) };
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn attach(connection: &X11Connection, shmid: i32) -> WincResult<shm::Seg> {
+        let conn = &connection.connection;
+        let seg = conn.generate_id()?;
+        conn.shm_attach(seg, shmid as u32, false)?.check()?;
+        Ok(seg)
+    }
+}
+
+pub struct X11RegionCapturer {
+    pub monitor: Rc<X11Monitor>,
+    pub capture_region: Rect,
+    pub alpha_policy: AlphaPolicy,
+    shm: Option<ShmSegment>,
+}
+
+pub fn get_full_x11_monitor_capturers(
+    connection: Rc<X11Connection>,
+) -> WincResult<Vec<X11RegionCapturer>> {
+    let monitors = get_all_x11_monitors(connection)?;
+    let mut capturers = Vec::new();
+
+    for monitor in monitors {
+        let region = monitor.info.rect;
+        let capturer = get_x11_monitor_capturer(Rc::new(monitor), region);
+        capturers.push(capturer);
+    }
+
+    Ok(capturers)
+}
+
+/// Create a capturer for a region, using MIT-SHM when the server supports it and XGetImage otherwise
+pub fn get_x11_monitor_capturer(
+    monitor: Rc<X11Monitor>,
+    capture_region: Rect,
+) -> X11RegionCapturer {
+    let size = (capture_region.width() * capture_region.height()) as usize * 4;
+    // the server refuses to attach remote clients' segments, so failure here just means no shm
+    let shm = if monitor.connection.has_shm && size > 0 {
+        ShmSegment::new(&monitor.connection, size).ok()
+    } else {
+        None
+    };
+    X11RegionCapturer {
+        monitor,
+        capture_region,
+        alpha_policy: AlphaPolicy::default(),
+        shm,
+    }
+}
+
+/// X11 equivalent of [`get_multi_monitor_capturer`](crate::prelude::get_multi_monitor_capturer)
+pub fn get_x11_multi_monitor_capturer(
+    monitors: &[Rc<X11Monitor>],
+    capture_region: Rect,
+) -> WincResult<StitchedCapturer<X11RegionCapturer>> {
+    let pieces = monitors
+        .iter()
+        .filter_map(|monitor| {
+            let piece = monitor.info.rect.intersection(&capture_region)?;
+            Some(get_x11_monitor_capturer(monitor.clone(), piece))
+        })
+        .collect();
+    get_stitched_capturer(pieces, capture_region)
+}
+
+/// X11 equivalent of [`capture_virtual_screen`](crate::prelude::capture_virtual_screen)
+pub fn capture_x11_virtual_screen(
+    connection: Rc<X11Connection>,
+    options: &VirtualScreenOptions,
+    metrics: &mut Metrics,
+) -> WincResult<Frame> {
+    let capturers = get_all_x11_monitors(connection)?
+        .into_iter()
+        .map(|monitor| {
+            let region = options.monitor_region(&monitor.info);
+            get_x11_monitor_capturer(Rc::new(monitor), region)
+        })
+        .collect();
+    capture_virtual_screen_from(capturers, options, metrics)
+}
+
+impl Drop for X11RegionCapturer {
+    fn drop(&mut self) {
+        if let Some(shm) = self.shm.take() {
+            self.detach(shm);
+        }
+    }
+}
+
+impl X11RegionCapturer {
+    /// Change what happens to alpha, [`AlphaPolicy::ForceOpaque`] by default
+    pub fn with_alpha_policy(mut self, alpha_policy: AlphaPolicy) -> Self {
+        self.alpha_policy = alpha_policy;
+        self
+    }
+
+    /// Stop using MIT-SHM and capture with XGetImage instead
+    pub fn without_shm(mut self) -> Self {
+        if let Some(shm) = self.shm.take() {
+            self.detach(shm);
+        }
+        self
+    }
+
+    fn detach(&self, shm: ShmSegment) {
+        let conn = &self.monitor.connection.connection;
+        if let Err(e) = conn.shm_detach(shm.seg).map(|cookie| cookie.ignore_error()) {
+            eprintln!("winc error detaching shared memory: {:?}", e);
+        }
+        unsafe { libc::shmdt(shm.addr as *const _) };
+    }
+
+    /// Whether captures go through MIT-SHM rather than XGetImage
+    pub fn uses_shm(&self) -> bool {
+        self.shm.is_some()
+    }
+
+    #[cfg(feature = "image")]
+    pub fn capture(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        CaptureSource::capture(self, metrics)
+    }
+
+    pub fn capture_raw(&self, metrics: &mut Metrics) -> WincResult<Frame> {
+        self.capture_as(NATIVE_PIXEL_FORMAT, metrics)
+    }
+
+    pub fn capture_as(&self, format: PixelFormat, metrics: &mut Metrics) -> WincResult<Frame> {
+        CaptureSource::capture_as(self, format, metrics)
+    }
+
+    pub fn capture_into(
+        &self,
+        frame: &mut FrameBuffer,
+        metrics: &mut Metrics,
+    ) -> WincResult<FrameInfo> {
+        CaptureSource::capture_into(self, frame, metrics)
+    }
+
+    pub fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        let width = self.capture_region.width();
+        let height = self.capture_region.height();
+        check_buffer_size((width * height) as usize * 4, buffer.len())?;
+        // unlike GDI, the server errors instead of returning black for pixels off the screen
+        let bounds = self.monitor.connection.bounds;
+        let region = self.capture_region;
+        if region.left < bounds.left
+            || region.top < bounds.top
+            || region.right > bounds.right
+            || region.bottom > bounds.bottom
+        {
+            return Err(WincError::RegionOutOfBounds { region, bounds });
+        }
+        let conn = &self.monitor.connection.connection;
+        let root = self.monitor.connection.root;
+        // root window coordinates are virtual desktop coordinates, no need to offset by the monitor
+        let x = self.capture_region.left() as i16;
+        let y = self.capture_region.top() as i16;
+
+        match &self.shm {
+            Some(shm) => {
+                metrics.begin("shmgetimage");
+                conn.shm_get_image(
+                    root,
+                    x,
+                    y,
+                    width as u16,
+                    height as u16,
+                    !0,
+                    ImageFormat::Z_PIXMAP.into(),
+                    shm.seg,
+                    0,
+                )?
+                .reply()?;
+                metrics.end("shmgetimage");
+
+                metrics.begin("copy");
+                let data = unsafe { std::slice::from_raw_parts(shm.addr, shm.size) };
+                buffer.copy_from_slice(data);
+                metrics.end("copy");
+            }
+            None => {
+                metrics.begin("getimage");
+                let reply = conn
+                    .get_image(
+                        ImageFormat::Z_PIXMAP,
+                        root,
+                        x,
+                        y,
+                        width as u16,
+                        height as u16,
+                        !0,
+                    )?
+                    .reply()?;
+                metrics.end("getimage");
+                check_buffer_size(buffer.len(), reply.data.len())?;
+                buffer.copy_from_slice(&reply.data);
+            }
+        }
+
+        // the padding byte of a 24 bit visual is undefined, the alpha policy makes it opaque
+        Ok(())
+    }
+}
+
+impl CaptureSource for X11RegionCapturer {
+    fn capture_bgra_into(&self, buffer: &mut [u8], metrics: &mut Metrics) -> WincResult<()> {
+        X11RegionCapturer::capture_bgra_into(self, buffer, metrics)
+    }
+
+    fn region(&self) -> Rect {
+        self.capture_region
+    }
+
+    fn monitor_info(&self) -> &MonitorInfo {
+        &self.monitor.info
+    }
+
+    fn alpha_policy(&self) -> AlphaPolicy {
+        self.alpha_policy
+    }
+}